@@ -1,5 +1,10 @@
 
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce, aead::{Aead, Payload}};
 
 use base64::{Engine as _, engine::{self, general_purpose}, alphabet};
 
@@ -105,6 +110,178 @@ pub fn map_strvalue(map: &HashMap<String, String>, key: &str, default: &str) ->
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldErrorKind {
+    Missing,
+    Invalid { value: String, expected: &'static str },
+    OutOfRange { value: String, expected: &'static str },
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub key: String,
+    pub kind: FieldErrorKind,
+}
+
+
+impl FieldError {
+
+    pub fn missing(key: &str) -> Self {
+        Self { key: key.to_string(), kind: FieldErrorKind::Missing }
+    }
+
+    pub fn invalid(key: &str, value: impl ToString, expected: &'static str) -> Self {
+        Self { key: key.to_string(), kind: FieldErrorKind::Invalid { value: value.to_string(), expected } }
+    }
+
+    pub fn out_of_range(key: &str, value: impl ToString, expected: &'static str) -> Self {
+        Self { key: key.to_string(), kind: FieldErrorKind::OutOfRange { value: value.to_string(), expected } }
+    }
+
+}
+
+
+impl fmt::Display for FieldErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "is missing"),
+            Self::Invalid { value, expected } => write!(f, "expected {expected}, found '{value}'"),
+            Self::OutOfRange { value, expected } => write!(f, "value {value} is out of range for {expected}"),
+        }
+    }
+}
+
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' {}", self.key, self.kind)
+    }
+}
+
+
+impl std::error::Error for FieldError {}
+
+
+fn parse_strfield<T: FromStr>(map: &HashMap<String, String>, key: &str, expected: &'static str) -> Result<T, FieldError> {
+    let raw = map.get(key).ok_or_else(|| FieldError::missing(key))?;
+    raw.trim().parse::<T>().map_err(|_| FieldError::invalid(key, raw, expected))
+}
+
+
+// accepts plain seconds ("30") or a number with a unit suffix ("250ms", "30s", "5m", "2h", "1d")...
+pub fn parse_duration(stringin: &str) -> Option<Duration> {
+    let trimmed = stringin.trim();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86400.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+
+// typed access for string maps such as headers, query strings and environment variables,
+// mirroring the maputils::val_to* helpers for json maps...
+pub trait StrMapExt {
+
+    fn try_get_u64(&self, key: &str) -> Result<u64, FieldError>;
+    fn try_get_i64(&self, key: &str) -> Result<i64, FieldError>;
+    fn try_get_u32(&self, key: &str) -> Result<u32, FieldError>;
+    fn try_get_f64(&self, key: &str) -> Result<f64, FieldError>;
+    fn try_get_bool(&self, key: &str) -> Result<bool, FieldError>;
+    fn try_get_list(&self, key: &str, separator: char) -> Result<Vec<String>, FieldError>;
+    fn try_get_duration(&self, key: &str) -> Result<Duration, FieldError>;
+
+    fn get_str(&self, key: &str, default: &str) -> String;
+
+    fn get_u64(&self, key: &str, default: u64) -> u64 {
+        self.try_get_u64(key).unwrap_or(default)
+    }
+
+    fn get_i64(&self, key: &str, default: i64) -> i64 {
+        self.try_get_i64(key).unwrap_or(default)
+    }
+
+    fn get_u32(&self, key: &str, default: u32) -> u32 {
+        self.try_get_u32(key).unwrap_or(default)
+    }
+
+    fn get_f64(&self, key: &str, default: f64) -> f64 {
+        self.try_get_f64(key).unwrap_or(default)
+    }
+
+    fn get_bool(&self, key: &str, default: bool) -> bool {
+        self.try_get_bool(key).unwrap_or(default)
+    }
+
+    fn get_list(&self, key: &str, separator: char, default: &[&str]) -> Vec<String> {
+        self.try_get_list(key, separator)
+            .unwrap_or_else(|_| default.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn get_duration(&self, key: &str, default: Duration) -> Duration {
+        self.try_get_duration(key).unwrap_or(default)
+    }
+
+}
+
+
+impl StrMapExt for HashMap<String, String> {
+
+    fn try_get_u64(&self, key: &str) -> Result<u64, FieldError> {
+        parse_strfield(self, key, "u64")
+    }
+
+    fn try_get_i64(&self, key: &str) -> Result<i64, FieldError> {
+        parse_strfield(self, key, "i64")
+    }
+
+    fn try_get_u32(&self, key: &str) -> Result<u32, FieldError> {
+        parse_strfield(self, key, "u32")
+    }
+
+    fn try_get_f64(&self, key: &str) -> Result<f64, FieldError> {
+        parse_strfield(self, key, "f64")
+    }
+
+    fn try_get_bool(&self, key: &str) -> Result<bool, FieldError> {
+        let raw = self.get(key).ok_or_else(|| FieldError::missing(key))?;
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(FieldError::invalid(key, raw, "bool")),
+        }
+    }
+
+    fn try_get_list(&self, key: &str, separator: char) -> Result<Vec<String>, FieldError> {
+        let raw = self.get(key).ok_or_else(|| FieldError::missing(key))?;
+        Ok(raw
+            .split(separator)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn try_get_duration(&self, key: &str) -> Result<Duration, FieldError> {
+        let raw = self.get(key).ok_or_else(|| FieldError::missing(key))?;
+        parse_duration(raw).ok_or_else(|| FieldError::invalid(key, raw, "duration"))
+    }
+
+    fn get_str(&self, key: &str, default: &str) -> String {
+        map_strvalue(self, key, default)
+    }
+
+}
+
+
 pub fn get_topt_token(secret: &str, epoch: i64) -> String {
     let totp = TOTP::new(
         Algorithm::SHA1,
@@ -143,6 +320,186 @@ pub fn md5(bytes : &[u8]) -> String {
 }
 
 
+// authenticated encryption for small payloads (cookies, secrets at rest)...
+// layout before base64: [version][key id length][key id][nonce][ciphertext + tag]
+
+const CRYPTO_VERSION: u8 = 1;
+const CRYPTO_NONCE_LEN: usize = 12;
+
+
+#[derive(Debug)]
+pub enum CryptoError {
+    InvalidKey,
+    KeyIdTooLong,
+    Encoding,
+    Header,
+    UnsupportedVersion(u8),
+    UnknownKey(String),
+    Encrypt,
+    Decrypt,
+    Json(serde_json::Error),
+}
+
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKey => write!(f, "key must be 32 bytes"),
+            Self::KeyIdTooLong => write!(f, "key id must be at most 255 bytes"),
+            Self::Encoding => write!(f, "payload is not valid base64"),
+            Self::Header => write!(f, "payload header is truncated or malformed"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported payload version {v}"),
+            Self::UnknownKey(id) => write!(f, "no key found with id '{id}'"),
+            Self::Encrypt => write!(f, "encryption failed"),
+            Self::Decrypt => write!(f, "decryption failed, payload was tampered with or key is wrong"),
+            Self::Json(e) => write!(f, "json error: {e}"),
+        }
+    }
+}
+
+
+impl std::error::Error for CryptoError {}
+
+
+#[derive(Clone)]
+pub struct CryptoKey {
+    id: String,
+    key: [u8; 32],
+}
+
+
+// never print key material...
+impl fmt::Debug for CryptoKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CryptoKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+
+impl CryptoKey {
+
+    pub fn new(id: &str, key: [u8; 32]) -> Result<Self, CryptoError> {
+        if id.len() > u8::MAX as usize {
+            return Err(CryptoError::KeyIdTooLong);
+        }
+        Ok(Self { id: id.to_string(), key })
+    }
+
+    pub fn from_bytes(id: &str, bytesin: &[u8]) -> Result<Self, CryptoError> {
+        let key: [u8; 32] = bytesin.try_into().map_err(|_| CryptoError::InvalidKey)?;
+        Self::new(id, key)
+    }
+
+    pub fn from_base64(id: &str, stringin: &str) -> Result<Self, CryptoError> {
+        Self::from_bytes(id, &b64_decode(stringin))
+    }
+
+    pub fn generate(id: &str) -> Result<Self, CryptoError> {
+        let mut key = [0u8; 32];
+        rand::fill(&mut key);
+        Self::new(id, key)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn to_base64(&self) -> String {
+        b64_encode_bytes(&self.key)
+    }
+
+}
+
+
+pub fn encrypt_bytes(key: &CryptoKey, bytesin: &[u8]) -> Result<String, CryptoError> {
+
+    let mut header = Vec::with_capacity(2 + key.id.len());
+    header.push(CRYPTO_VERSION);
+    header.push(key.id.len() as u8);
+    header.extend_from_slice(key.id.as_bytes());
+
+    let mut nonce = [0u8; CRYPTO_NONCE_LEN];
+    rand::fill(&mut nonce);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: bytesin, aad: &header })
+        .map_err(|_| CryptoError::Encrypt)?;
+
+    let mut out = header;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+
+    Ok(b64_encode_withengine(&out, general_purpose::URL_SAFE_NO_PAD))
+
+}
+
+
+// reads the key id from a payload without decrypting it, useful for key rotation...
+pub fn encrypted_key_id(stringin: &str) -> Result<String, CryptoError> {
+    let raw = b64_decode_withengine(stringin, general_purpose::URL_SAFE_NO_PAD);
+    let (_, id, _) = split_encrypted(&raw)?;
+    Ok(id.to_string())
+}
+
+
+fn split_encrypted(raw: &[u8]) -> Result<(&[u8], &str, &[u8]), CryptoError> {
+
+    if raw.is_empty() {
+        return Err(CryptoError::Encoding);
+    }
+
+    if raw.len() < 2 {
+        return Err(CryptoError::Header);
+    }
+
+    if raw[0] != CRYPTO_VERSION {
+        return Err(CryptoError::UnsupportedVersion(raw[0]));
+    }
+
+    let header_len = 2 + raw[1] as usize;
+    if raw.len() < header_len + CRYPTO_NONCE_LEN {
+        return Err(CryptoError::Header);
+    }
+
+    let id = std::str::from_utf8(&raw[2..header_len]).map_err(|_| CryptoError::Header)?;
+    Ok((&raw[..header_len], id, &raw[header_len..]))
+
+}
+
+
+pub fn decrypt_bytes(keys: &[CryptoKey], stringin: &str) -> Result<Vec<u8>, CryptoError> {
+
+    let raw = b64_decode_withengine(stringin, general_purpose::URL_SAFE_NO_PAD);
+    let (header, id, body) = split_encrypted(&raw)?;
+
+    let key = keys
+        .iter()
+        .find(|k| k.id == id)
+        .ok_or_else(|| CryptoError::UnknownKey(id.to_string()))?;
+
+    let (nonce, sealed) = body.split_at(CRYPTO_NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad: header })
+        .map_err(|_| CryptoError::Decrypt)
+
+}
+
+
+pub fn encrypt_value(key: &CryptoKey, valuein: &Value) -> Result<String, CryptoError> {
+    let bytes = serde_json::to_vec(valuein).map_err(CryptoError::Json)?;
+    encrypt_bytes(key, &bytes)
+}
+
+
+pub fn decrypt_value(keys: &[CryptoKey], stringin: &str) -> Result<Value, CryptoError> {
+    let bytes = decrypt_bytes(keys, stringin)?;
+    serde_json::from_slice(&bytes).map_err(CryptoError::Json)
+}
+
+
 #[cfg(test)]
 mod test {
 
@@ -178,4 +535,51 @@ mod test {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn encryptiontests() {
+
+        let key = super::CryptoKey::generate("k1").unwrap();
+        let other = super::CryptoKey::generate("k2").unwrap();
+
+        let value = serde_json::json!({"user": "sam", "roles": ["admin"]});
+        let sealed = super::encrypt_value(&key, &value).unwrap();
+
+        assert_eq!(super::encrypted_key_id(&sealed).unwrap(), "k1");
+        assert_eq!(super::decrypt_value(&[other.clone(), key.clone()], &sealed).unwrap(), value);
+
+        assert!(matches!(super::decrypt_bytes(std::slice::from_ref(&other), &sealed), Err(super::CryptoError::UnknownKey(_))));
+
+        let forged = super::CryptoKey::from_base64("k1", &other.to_base64()).unwrap();
+        assert!(matches!(super::decrypt_bytes(&[forged], &sealed), Err(super::CryptoError::Decrypt)));
+
+        assert!(matches!(super::decrypt_bytes(&[key], "!!!"), Err(super::CryptoError::Encoding)));
+
+    }
+
+    #[test]
+    fn strmaptests() {
+
+        use std::collections::HashMap;
+        use std::time::Duration;
+        use super::{StrMapExt, FieldError};
+
+        let mut map: HashMap<String, String> = HashMap::new();
+        map.insert("limit".to_string(), "25".to_string());
+        map.insert("debug".to_string(), "Yes".to_string());
+        map.insert("tags".to_string(), "a, b,,c".to_string());
+        map.insert("timeout".to_string(), "1500ms".to_string());
+        map.insert("bad".to_string(), "abc".to_string());
+
+        assert_eq!(map.get_u64("limit", 10), 25);
+        assert_eq!(map.get_u64("bad", 10), 10);
+        assert!(map.get_bool("debug", false));
+        assert_eq!(map.get_list("tags", ',', &[]), vec!["a", "b", "c"]);
+        assert_eq!(map.get_list("none", ',', &["x"]), vec!["x"]);
+        assert_eq!(map.get_duration("timeout", Duration::ZERO), Duration::from_millis(1500));
+
+        assert_eq!(map.try_get_u32("missing"), Err(FieldError::missing("missing")));
+        assert_eq!(map.try_get_u32("bad"), Err(FieldError::invalid("bad", "abc", "u32")));
+
+    }
+
+}
@@ -1,9 +1,16 @@
 
+use std::fmt;
 use std::sync::LazyLock;
 
 use serde_json::{Value};
 use serde::{Serialize};
 
+pub use util_lib_derive::EnumType;
+
+// used by the code #[derive(EnumType)] generates...
+#[doc(hidden)]
+pub use serde as __serde;
+
 
 #[macro_export]
 macro_rules! enum_values_lazy_slice {
@@ -39,23 +46,64 @@ pub trait Base64Transform <T: Serialize + Clone> {
 }
 
 
-pub trait EnumType 
-where Self: Sized + Copy + 'static + Into<u8> + From<u8> {
-    
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumTypeError {
+    pub type_name: &'static str,
+    pub value: String,
+}
+
+
+impl fmt::Display for EnumTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a valid {}", self.value, self.type_name)
+    }
+}
+
+
+impl std::error::Error for EnumTypeError {}
+
+
+// names map to variants explicitly through VARIANTS, so discriminants may have gaps and the
+// repr can be any of u8 / u16 / u32 / u64. #[derive(EnumType)] fills all of this in...
+pub trait EnumType
+where Self: Sized + Copy + PartialEq + 'static {
+
+    type Repr: Copy + Eq;
+
+    // every variant with its name, in declaration order...
+    const VARIANTS: &'static [(Self, &'static str)];
+
     fn default() -> Self;
 
-    fn as_vec() -> Vec<&'static str>;
+    fn to_repr(self) -> Self::Repr;
 
+    fn from_repr(value: Self::Repr) -> Option<Self> {
+        Self::iter_variants().find(|variant| variant.to_repr() == value)
+    }
+
+    fn iter_variants() -> impl Iterator<Item = Self> {
+        Self::VARIANTS.iter().map(|(variant, _)| *variant)
+    }
+
+    fn as_vec() -> Vec<&'static str> {
+        Self::VARIANTS.iter().map(|(_, name)| *name).collect()
+    }
+
+    fn try_from_name(s: &str) -> Result<Self, EnumTypeError> {
+        Self::VARIANTS
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(variant, _)| *variant)
+            .ok_or_else(|| EnumTypeError { type_name: std::any::type_name::<Self>(), value: s.to_string() })
+    }
+
+    // unknown names fall back to the default variant...
     fn from_str(s: &str) -> Self {
-        if let Some(p) = Self::as_vec().iter().position(|n| *n == s) {
-            (p as u8).into()
-        } else {
-            Self::default()
-        }
+        Self::try_from_name(s).unwrap_or_else(|_| Self::default())
     }
 
-    fn to_str(&self) -> &str {
-        Self::as_vec()[usize::from((*self).into())]
+    fn to_str(&self) -> &'static str {
+        Self::VARIANTS.iter().find(|(variant, _)| variant == self).map_or("", |(_, name)| name)
     }
 
     fn str_value(self) -> String {
@@ -63,4 +111,72 @@ where Self: Sized + Copy + 'static + Into<u8> + From<u8> {
     }
 
 }
-  
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{EnumType, EnumTypeError};
+    use crate::httputils::{HttpEncoding, HttpMethod, HttpResponseCodes};
+
+    #[derive(Debug, PartialEq, Clone, Copy, EnumType)]
+    #[repr(u8)]
+    enum Level {
+        Low,
+        #[enum_type(default)]
+        Medium,
+        #[enum_type(rename = "very-high")]
+        High,
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy, EnumType)]
+    #[repr(u32)]
+    enum Sparse {
+        First = 10,
+        Second,
+        Far = 70000,
+    }
+
+    #[test]
+    fn enumtypetests() {
+
+        assert_eq!(Level::as_vec(), vec!["low", "medium", "very-high"]);
+        assert_eq!(<Level as EnumType>::default(), Level::Medium);
+        assert_eq!(Level::from(2u8), Level::High);
+        assert_eq!(Level::from(9u8), Level::Medium);
+        assert_eq!(u8::from(Level::High), 2);
+        assert_eq!(Level::from_str("very-high"), Level::High);
+        assert_eq!(Level::from_str("unknown"), Level::Medium);
+        assert_eq!(Level::Low.to_str(), "low");
+
+        assert_eq!(serde_json::to_value(Level::High).unwrap(), json!("very-high"));
+        assert_eq!(serde_json::from_value::<Level>(json!("low")).unwrap(), Level::Low);
+        assert!(serde_json::from_value::<Level>(json!("extreme")).is_err());
+
+        assert_eq!(Sparse::iter_variants().collect::<Vec<_>>(), vec![Sparse::First, Sparse::Second, Sparse::Far]);
+        assert_eq!(Sparse::Second.to_repr(), 11);
+        assert_eq!(Sparse::from_repr(70000), Some(Sparse::Far));
+        assert_eq!(Sparse::from_repr(12), None);
+        assert_eq!(Sparse::Far.to_str(), "far");
+        assert_eq!(Sparse::try_from("second"), Ok(Sparse::Second));
+        assert_eq!(Sparse::try_from("third").unwrap_err().value, "third");
+
+        assert_eq!(HttpResponseCodes::NotFound.to_str(), "notfound");
+        assert_eq!(HttpResponseCodes::from(503u16), HttpResponseCodes::ServiceUnavailable);
+        assert_eq!(HttpResponseCodes::from(299u16), HttpResponseCodes::InternalServerError);
+        assert_eq!(u16::from(HttpResponseCodes::NetworkAuthenticationRequired), 511);
+        assert_eq!(serde_json::to_value(HttpResponseCodes::OK).unwrap(), json!("OK"));
+
+        assert_eq!(HttpEncoding::Brotli.to_str(), "br");
+        assert_eq!(HttpEncoding::try_from_name("zstd"), Ok(HttpEncoding::Zstandard));
+        assert_eq!(HttpEncoding::from("gzip"), HttpEncoding::Gzip);
+        assert_eq!(serde_json::to_value(HttpEncoding::Brotli).unwrap(), json!("Brotli"));
+        assert!(matches!(HttpMethod::try_from("patch"), Err(EnumTypeError { .. })));
+        assert_eq!(HttpMethod::from_str("post"), HttpMethod::Post);
+
+    }
+
+}
@@ -5,9 +5,9 @@ use std::collections::HashMap;
 
 use warp::http::HeaderMap;
 
-use flate2::{write::GzEncoder, Compression};
+use flate2::{write::{DeflateEncoder, GzEncoder}, Compression};
 use brotli::CompressorWriter;
-use std::io::Write;
+use std::io::{self, Write};
 
 use serde_json::{Map, Value};
 use serde::{Deserialize, Serialize};
@@ -22,7 +22,8 @@ use crate::serdeutils::{fetch_string};
 
 // http return codes...
 
-#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone, Copy, EnumType)]
+#[enum_type(skip_serde)]
 #[repr(u16)]
 pub enum HttpResponseCodes {
 	Continue = 100,
@@ -76,6 +77,7 @@ pub enum HttpResponseCodes {
     TooManyRequests,
     RequestHeaderFieldsTooLarge = 431,
     UnavailableForLegalReasons,
+    #[enum_type(default)]
     InternalServerError = 500,
     NotImplemented,
     BadGateway,
@@ -89,13 +91,6 @@ pub enum HttpResponseCodes {
     NetworkAuthenticationRequired
 }
 
-impl From<HttpResponseCodes> for u16 {
-    fn from(val: HttpResponseCodes) -> Self {
-        val as u16
-    }
-}
-
-
 pub fn headermap_tohashmap(
     
     mapin: &HeaderMap
@@ -217,7 +212,7 @@ pub fn lookup_mime(extension: &str) -> &str {
 }
 
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumType)]
 #[repr(u8)]
 pub enum HttpMethod {
     Undefined = 0,
@@ -232,43 +227,6 @@ pub enum HttpMethod {
 }
 
 
-impl Into<u8> for HttpMethod {
-    fn into(self) -> u8 {
-        self as u8
-    }
-}
-
-
-impl From<u8> for HttpMethod {
-    fn from(value: u8) -> Self {
-        match value {
-            1 => Self::Gateway,
-            2 => Self::Get,
-            3 => Self::Post,
-            4 => Self::Put,
-            5 => Self::Delete,
-            6 => Self::User,
-            7 => Self::Head,
-            8 => Self::Sock,
-            _ => Self::Undefined
-        }
-    }
-}
-
-
-impl EnumType for HttpMethod {
-
-    fn as_vec() -> Vec<&'static str> {
-        ["undefined", "gateway", "get", "post", "put", "delete", "user", "head", "sock"].to_vec()
-    }
-
-    fn default() -> Self {
-        Self::Undefined
-    }
-
-}
-
-
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WebRepeaterDef {
     pub display_as: Option<String>,
@@ -367,52 +325,21 @@ pub fn cleanup_html(stringin: &str) -> String {
 
 
 
-#[derive(PartialEq, Debug, Serialize, Eq, Clone, Copy)]
+#[derive(PartialEq, Debug, Serialize, Eq, Clone, Copy, EnumType)]
+#[enum_type(skip_serde, skip_try_from)]
 #[repr(u8)]
 pub enum HttpEncoding {
+    #[enum_type(rename = "br")]
     Brotli = 0,
     Gzip = 1,
     Deflate = 2, 
+    #[enum_type(rename = "zstd")]
     Zstandard = 3,
+    #[enum_type(default)]
     Identity = 4,
 }
 
 
-impl From<u8> for HttpEncoding {
-    fn from(value: u8) -> Self {
-        match value {
-            0 => Self::Brotli,
-            1 => Self::Gzip,
-            2 => Self::Deflate,
-            3 => Self::Zstandard,
-            _ => Self::Identity
-        }
-    }
-}
-
-impl Into<u8> for HttpEncoding {
-    fn into(self) -> u8 {
-        self as u8
-    }
-}
-
-
-const SUPPORTED_ENCODINGS: &[&str] = &["br", "gzip", "deflate", "zstd", "identity"];
-
-
-impl EnumType for HttpEncoding {
-    
-    fn as_vec() -> Vec<&'static str> {
-        SUPPORTED_ENCODINGS.to_vec()
-    }
-
-    fn default() -> Self {
-        Self::Identity
-    }
-
-}
-
-
 impl From<&str> for HttpEncoding {
     
     fn from(value: &str) -> Self {
@@ -441,7 +368,7 @@ impl HttpEncoding {
             },
 
             Self::Deflate => {
-                let mut _encoder = flate2::write::DeflateEncoder::new(Vec::new(), Compression::default());
+                let mut _encoder = DeflateEncoder::new(Vec::new(), Compression::default());
                 _encoder.write_all(body).unwrap();
                 _encoder.finish().unwrap()
             },
@@ -470,6 +397,72 @@ impl HttpEncoding {
 }
 
 
+// streaming counterpart to HttpEncoding::compress, call finish() to flush the trailer...
+pub enum EncodingWriter<W: Write> {
+    Brotli(Box<CompressorWriter<W>>),
+    Gzip(GzEncoder<W>),
+    Deflate(DeflateEncoder<W>),
+    Zstandard(zstd::stream::Encoder<'static, W>),
+    Identity(W),
+}
+
+
+impl <W: Write> EncodingWriter<W> {
+
+    pub fn new(encoding: HttpEncoding, inner: W) -> io::Result<Self> {
+        Ok(match encoding {
+            HttpEncoding::Brotli => Self::Brotli(Box::new(CompressorWriter::new(inner, 4096, 11, 22))),
+            HttpEncoding::Gzip => Self::Gzip(GzEncoder::new(inner, Compression::default())),
+            HttpEncoding::Deflate => Self::Deflate(DeflateEncoder::new(inner, Compression::default())),
+            HttpEncoding::Zstandard => Self::Zstandard(zstd::stream::Encoder::new(inner, 0)?),
+            HttpEncoding::Identity => Self::Identity(inner),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Brotli(mut writer) => {
+                writer.flush()?;
+                Ok(writer.into_inner())
+            },
+            Self::Gzip(writer) => writer.finish(),
+            Self::Deflate(writer) => writer.finish(),
+            Self::Zstandard(writer) => writer.finish(),
+            Self::Identity(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            },
+        }
+    }
+
+}
+
+
+impl <W: Write> Write for EncodingWriter<W> {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Brotli(writer) => writer.write(buf),
+            Self::Gzip(writer) => writer.write(buf),
+            Self::Deflate(writer) => writer.write(buf),
+            Self::Zstandard(writer) => writer.write(buf),
+            Self::Identity(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Brotli(writer) => writer.flush(),
+            Self::Gzip(writer) => writer.flush(),
+            Self::Deflate(writer) => writer.flush(),
+            Self::Zstandard(writer) => writer.flush(),
+            Self::Identity(writer) => writer.flush(),
+        }
+    }
+
+}
+
+
 pub fn determine_encoding(header: Option<String>) -> HttpEncoding {
 
     match header {
@@ -499,8 +492,8 @@ pub fn determine_encoding(header: Option<String>) -> HttpEncoding {
 
             // Return the first matching supported encoding
             for (_encoding, _) in _encodings {
-                if SUPPORTED_ENCODINGS.contains(&_encoding.as_str()) {
-                    return _encoding.as_str().into();
+                if let Ok(encoding) = HttpEncoding::try_from_name(&_encoding) {
+                    return encoding;
                 }
             }
 
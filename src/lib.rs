@@ -1,6 +1,9 @@
 
 #![allow(unused, reason = "")]
 
+// lets #[derive(EnumType)] name ::util_lib paths from inside this crate too...
+extern crate self as util_lib;
+
 pub mod gentraits;
 pub mod btree;
 pub mod genericutils;
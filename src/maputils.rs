@@ -4,107 +4,264 @@ use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+use chrono::{DateTime, Utc};
+
+use crate::genericutils::FieldError;
+use crate::serdeutils::{FromLenient, PathSegment, get_segments, parse_path, remove_segments, set_segments};
+
+pub mod projection;
+pub mod merge;
+pub mod casing;
+pub mod records;
+pub mod filter;
+pub mod csv;
+
+pub use projection::Projection;
+pub use casing::{KeyCase, convert_case, convert_keys, convert_map_keys};
+pub use csv::{CsvError, CsvOptions, CsvReader, CsvWriter, from_csv, to_csv};
+pub use filter::{Filter, FilterError};
+pub use records::{Aggregate, JoinKind, Record, SortKey, aggregate, distinct, group_aggregate, group_by, index_by, join, sort_records};
+pub use merge::{ArrayStrategy, ConflictStrategy, MergeError, MergeReport, MergeStrategy, deep_merge, deep_merge_all};
+
+
+// re-exported so the macros below work without serde_json imported at the call site...
+#[doc(hidden)]
+pub use serde_json as __serde_json;
+
 
 #[macro_export]
 macro_rules! map_add {
     ($x:expr, $y:expr, $z:expr) => {
-        $x.insert($y.to_string(), Value::from($z))
+        $x.insert($y.to_string(), $crate::maputils::__serde_json::Value::from($z))
     };
 }
 
 
-pub fn val_tostr(element: &Map<String, Value>, key: &str, default: &str) -> String {
-    if let Some(_k) = element.get(key) {
-        if *_k == Value::Null {
-            default.to_string()
-        } else {
-            _k.as_str().expect("").to_string()
+// builds a Map<String, Value> in one expression...
+//
+//   json_map!{
+//       "id" => 12,
+//       "nickname" => nickname?,             skipped when the Option is None
+//       "admin" if user.is_admin => true,    only inserted when the condition holds
+//       "address" => { "city" => city },    nested literal map
+//       ..defaults,                          copies every entry of another map
+//   }
+//
+// entries apply in order so later keys overwrite earlier ones, including spread entries.
+// keys are literals or any parenthesised expression implementing ToString. values are evaluated
+// in an Option context, so ? works anywhere in the value ("user.nick?.trim()") and None skips it.
+// that context is a closure, so values cannot use .await, return, break or continue, and ? only
+// works on Options, convert a Result with .ok()? or evaluate it before the macro.
+// the macro recurses once per entry, so one invocation takes at most 126 entries (spreads included)
+// under the default recursion limit of 128, a nested map uses the same budget from where it appears,
+// split larger maps or raise #![recursion_limit].
+#[macro_export]
+macro_rules! json_map {
+    (@entries $map:ident) => {};
+
+    (@entries $map:ident .. $other:expr $(, $($rest:tt)*)?) => {
+        $crate::maputils::map_spread(&mut $map, &$other);
+        $crate::json_map!(@entries $map $($($rest)*)?);
+    };
+
+    // a bare brace group is a nested map...
+    (@entries $map:ident $key:tt $(if $cond:expr)? => { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        if true $(&& ($cond))? {
+            $map.insert($key.to_string(), $crate::maputils::__serde_json::Value::Object($crate::json_map!{ $($inner)* }));
         }
-    } else {
-        default.to_string()
-    }
-} 
+        $crate::json_map!(@entries $map $($($rest)*)?);
+    };
 
-pub fn val_tou64(element: &Map<String, Value>, key: &str, default: u64) -> u64 {
-    match element.get(key) {
-        None => default,
-        Some(value) => {
-            if let Some(number) = value.as_u64() {
-                number
-            } else {
-                match value.as_str() {
-                    Some(number) => number.parse::<u64>().unwrap_or(default),
-                    None => default,
-                }
+    (@entries $map:ident $key:tt $(if $cond:expr)? => $value:expr $(, $($rest:tt)*)?) => {
+        if true $(&& ($cond))? {
+            #[allow(clippy::redundant_closure_call)]
+            let value = (|| ::std::option::Option::Some($value))();
+            if let ::std::option::Option::Some(value) = value {
+                $map.insert($key.to_string(), $crate::maputils::__serde_json::Value::from(value));
             }
         }
+        $crate::json_map!(@entries $map $($($rest)*)?);
+    };
+
+    ($($body:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::maputils::__serde_json::Map::new();
+        $crate::json_map!(@entries map $($body)*);
+        map
+    }};
+}
+
+
+// exact keys win, otherwise the key is read as a path ("address.lines[0]"), used by the
+// record helpers and MapExt::try_get_path...
+fn map_lookup<'a>(element: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    if let Some(value) = element.get(key) {
+        return Some(value);
     }
+    let segments = parse_path(key).ok()?;
+    let (PathSegment::Key(first), rest) = segments.split_first()? else {
+        return None;
+    };
+    get_segments(element.get(first)?, rest)
 }
 
-pub fn val_toi64(element: &Map<String, Value>, key: &str, default: i64) -> i64 {
-    match element.get(key) {
-        None => default,
-        Some(value) => {
-            if let Some(number) = value.as_i64() {
-                number
-            } else {
-                match value.as_str() {
-                    Some(number) => number.parse::<i64>().unwrap_or(default),
-                    None => default,
-                }
-            }
-        }
+
+fn lenient_field<T: FromLenient>(value: Option<&Value>, key: &str) -> Result<T, FieldError> {
+    match value {
+        None | Some(Value::Null) => T::from_missing().ok_or_else(|| FieldError::missing(key)),
+        Some(value) => T::from_lenient(value).map_err(|kind| FieldError { key: key.to_string(), kind }),
     }
 }
 
-pub fn val_tou32(element: &Map<String, Value>, key: &str, default: u32) -> u32 {    
-    match element.get(key) {
-        None => default,
-        Some(value) => {
-            if let Some(number) = value.as_u64() {
-                number as u32
-            } else {
-                match value.as_str() {
-                    Some(number) => number.parse::<u32>().unwrap_or(default),
-                    None => default,
-                }
-            }
+
+// typed, non panicking access to json maps using the serdeutils::FromLenient coercions,
+// try_get_* report what went wrong and get_* fall back to a default, keys are exact
+// top level keys, use try_get_path / get_path to read nested values ("address.city")...
+pub trait MapExt {
+
+    fn try_get<T: FromLenient>(&self, key: &str) -> Result<T, FieldError>;
+
+    fn try_get_path<T: FromLenient>(&self, path: &str) -> Result<T, FieldError>;
+
+    fn try_get_array(&self, key: &str) -> Result<&Vec<Value>, FieldError>;
+
+    fn try_get_object(&self, key: &str) -> Result<&Map<String, Value>, FieldError>;
+
+    fn try_get_str(&self, key: &str) -> Result<String, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_u64(&self, key: &str) -> Result<u64, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_i64(&self, key: &str) -> Result<i64, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_u32(&self, key: &str) -> Result<u32, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_f64(&self, key: &str) -> Result<f64, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_bool(&self, key: &str) -> Result<bool, FieldError> {
+        self.try_get(key)
+    }
+
+    fn try_get_datetime(&self, key: &str) -> Result<DateTime<Utc>, FieldError> {
+        self.try_get(key)
+    }
+
+    fn get_path<T: FromLenient>(&self, path: &str, default: T) -> T {
+        self.try_get_path(path).unwrap_or(default)
+    }
+
+    fn get_str(&self, key: &str, default: &str) -> String {
+        self.try_get_str(key).unwrap_or_else(|_| default.to_string())
+    }
+
+    fn get_u64(&self, key: &str, default: u64) -> u64 {
+        self.try_get_u64(key).unwrap_or(default)
+    }
+
+    fn get_i64(&self, key: &str, default: i64) -> i64 {
+        self.try_get_i64(key).unwrap_or(default)
+    }
+
+    fn get_u32(&self, key: &str, default: u32) -> u32 {
+        self.try_get_u32(key).unwrap_or(default)
+    }
+
+    fn get_f64(&self, key: &str, default: f64) -> f64 {
+        self.try_get_f64(key).unwrap_or(default)
+    }
+
+    fn get_bool(&self, key: &str, default: bool) -> bool {
+        self.try_get_bool(key).unwrap_or(default)
+    }
+
+    fn get_datetime(&self, key: &str) -> Option<DateTime<Utc>> {
+        self.try_get_datetime(key).ok()
+    }
+
+    fn get_array(&self, key: &str) -> Option<&Vec<Value>> {
+        self.try_get_array(key).ok()
+    }
+
+    fn get_object(&self, key: &str) -> Option<&Map<String, Value>> {
+        self.try_get_object(key).ok()
+    }
+
+}
+
+
+impl MapExt for Map<String, Value> {
+
+    fn try_get<T: FromLenient>(&self, key: &str) -> Result<T, FieldError> {
+        lenient_field(self.get(key), key)
+    }
+
+    fn try_get_path<T: FromLenient>(&self, path: &str) -> Result<T, FieldError> {
+        lenient_field(map_lookup(self, path), path)
+    }
+
+    fn try_get_array(&self, key: &str) -> Result<&Vec<Value>, FieldError> {
+        match self.get(key) {
+            None | Some(Value::Null) => Err(FieldError::missing(key)),
+            Some(Value::Array(values)) => Ok(values),
+            Some(other) => Err(FieldError::invalid(key, other, "array")),
         }
     }
+
+    fn try_get_object(&self, key: &str) -> Result<&Map<String, Value>, FieldError> {
+        match self.get(key) {
+            None | Some(Value::Null) => Err(FieldError::missing(key)),
+            Some(Value::Object(map)) => Ok(map),
+            Some(other) => Err(FieldError::invalid(key, other, "object")),
+        }
+    }
+
 }
 
+
+pub fn val_tostr(element: &Map<String, Value>, key: &str, default: &str) -> String {
+    element.get_str(key, default)
+}
+
+pub fn val_tou64(element: &Map<String, Value>, key: &str, default: u64) -> u64 {
+    element.get_u64(key, default)
+}
+
+pub fn val_toi64(element: &Map<String, Value>, key: &str, default: i64) -> i64 {
+    element.get_i64(key, default)
+}
+
+pub fn val_tou32(element: &Map<String, Value>, key: &str, default: u32) -> u32 {
+    element.get_u32(key, default)
+}
+
+// keeps the original coercion, only true / 1 / "1" are true, MapExt::get_bool also takes "true", "yes" etc...
 pub fn val_tobool(element: &Map<String, Value>, key: &str, default: bool) -> bool {
     match element.get(key) {
         None => default,
-        Some(value) => {
-            if let Some(boolean) = value.as_bool() {
-                boolean
-            } else if value.is_number() {
-                value.as_u64() == Some(1)
-            } else {
-                match value.as_str() {
-                    Some(boolean) => boolean.parse::<u64>().unwrap_or(0) == 1,
-                    None => default,
-                }
-            }
-        }
+        Some(Value::Bool(b)) => *b,
+        Some(value @ Value::Number(_)) => value.as_u64() == Some(1),
+        Some(Value::String(s)) => s.parse::<u64>().unwrap_or(0) == 1,
+        Some(_) => default,
     }
 }
 
 pub fn val_tofloat(element: &Map<String, Value>, key: &str, default: f64) -> f64 {
-    match element.get(key) {
-        None => default,
-        Some(value) => {
-            if let Some(number) = value.as_f64() {
-                number
-            } else {
-                match value.as_str() {
-                    Some(number) => number.parse::<f64>().unwrap_or(default),
-                    None => default,
-                }
-            }
-        }
-    }
+    element.get_f64(key, default)
+}
+
+
+// used by json_map! for ..other entries...
+pub fn map_spread(element: &mut Map<String, Value>, other: &Map<String, Value>) {
+    element.extend(other.iter().map(|(key, value)| (key.clone(), value.clone())));
 }
 
 
@@ -115,25 +272,141 @@ pub fn from_hashmap<T: Clone>(mapin: HashMap<String, T>) -> Map<String, Value> w
 }
 
 
-pub fn map_copy_exceptkeys(element: &Map<String, Value>, keys: Vec<&str>) -> Map<String, Value> {        
-    let lookup: HashSet<&str> = keys.into_iter().collect();
-    let mut out = Map::with_capacity(element.len());
-    for (key, value) in element {
-        if !lookup.contains(key.as_str()) {
-            out.insert(key.clone(), value.clone());
+// keys may be nested paths ("user.password"), exact top level keys are matched first...
+pub fn map_copy_exceptkeys<'a>(element: &Map<String, Value>, keys: impl IntoIterator<Item = &'a str>) -> Map<String, Value> {
+    let mut out = Value::Object(element.clone());
+    for key in keys {
+        if let Value::Object(map) = &mut out
+            && map.remove(key).is_some() {
+                continue;
+            }
+        if let Ok(segments) = parse_path(key) {
+            remove_segments(&mut out, &segments);
         }
     }
-    out
+    match out {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
 }
 
 
-pub fn map_copy_withkeys(element: &Map<String, Value>, keys: Vec<&str>) -> Map<String, Value> {
+pub fn map_copy_withkeys<'a>(element: &Map<String, Value>, keys: impl IntoIterator<Item = &'a str>) -> Map<String, Value> {
     let lookup: HashSet<&str> = keys.into_iter().collect();
-    let mut out = Map::with_capacity(element.len());
-    for (key, value) in element {
-        if lookup.contains(key.as_str()) {
-            out.insert(key.clone(), value.clone());
-        }
+    let mut out = Value::Object(Map::with_capacity(lookup.len()));
+    for key in lookup {
+        if let Some(value) = element.get(key) {
+            let _ = set_segments(&mut out, &[PathSegment::Key(key.to_string())], value.clone());
+        } else if let Ok(segments) = parse_path(key)
+            && let Some(value) = map_lookup(element, key) {
+                let _ = set_segments(&mut out, &segments, value.clone());
+            }
     }
-    out
-}
\ No newline at end of file
+    match out {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Map, Value, json};
+
+    use crate::genericutils::{FieldError, FieldErrorKind};
+
+    use super::{MapExt, val_tobool, val_tostr, val_tou32};
+
+    #[test]
+    fn mapexttests() {
+
+        let map: Map<String, Value> = serde_json::from_value(json!({
+            "name": "sam", "count": 12, "big": 5000000000u64, "flag": "true", "missing": null,
+            "when": "2024-01-02T03:04:05Z", "list": [1, 2], "nested": {"city": "leeds"}
+        })).unwrap();
+
+        assert_eq!(val_tostr(&map, "count", ""), "12");
+        assert_eq!(val_tostr(&map, "missing", "none"), "none");
+        assert_eq!(val_tou32(&map, "big", 7), 7);
+        assert!(!val_tobool(&map, "flag", false));
+        assert!(map.get_bool("flag", false));
+        assert!(!val_tobool(&map, "count", true) && val_tobool(&map, "missing", true));
+
+        assert!(matches!(map.try_get_u32("big").unwrap_err().kind, FieldErrorKind::OutOfRange { .. }));
+        assert_eq!(map.try_get_u64("absent"), Err(FieldError::missing("absent")));
+        assert_eq!(map.get_array("list").map(Vec::len), Some(2));
+        assert_eq!(map.get_object("nested").and_then(|o| o.get("city")), Some(&json!("leeds")));
+        assert!(map.try_get_array("name").is_err());
+        assert_eq!(map.get_str("nested.city", "none"), "none");
+        assert_eq!(val_tostr(&map, "nested.city", "none"), "none");
+        assert_eq!(map.get_path("nested.city", String::new()), "leeds");
+        assert_eq!(map.try_get_path::<u64>("list[1]"), Ok(2));
+
+        let dotted: Map<String, Value> = serde_json::from_value(json!({"a.b": 1, "a": {"b": 2}})).unwrap();
+        assert_eq!(val_tou32(&dotted, "a.b", 0), 1);
+        assert_eq!(dotted.get_path("a.b", 0u32), 1);
+        assert_eq!(dotted.get_path("a[\"b\"]", 0u32), 2);
+        assert_eq!(map.get_datetime("when").map(|d| d.timestamp()), Some(1704164645));
+
+        let picked = super::map_copy_withkeys(&map, vec!["name", "nested.city"]);
+        assert_eq!(Value::Object(picked), json!({"name": "sam", "nested": {"city": "leeds"}}));
+
+        let dropped = super::map_copy_exceptkeys(&map, ["nested.city", "list", "when", "big", "flag", "missing", "count"]);
+        assert_eq!(Value::Object(dropped), json!({"name": "sam", "nested": {}}));
+
+    }
+
+    #[test]
+    fn jsonmaptests() {
+
+        let nickname: Option<&str> = None;
+        let email = Some("sam@example.com");
+        let admin = false;
+        let key = "dynamic";
+        let defaults = crate::json_map!{ "theme" => "dark", "count" => 1 };
+
+        let map = crate::json_map!{
+            "count" => 0,
+            ..defaults,
+            "id" => 12u64,
+            "nickname" => nickname?,
+            "email" => email?,
+            "admin" if admin => true,
+            "tags" => vec!["a", "b"],
+            "address" => { "city" => "leeds", "zip" if !admin => "ls1" },
+            (key) => 1.5 * 2.0,
+        };
+
+        assert_eq!(Value::Object(map), json!({
+            "count": 1, "theme": "dark", "id": 12, "email": "sam@example.com", "tags": ["a", "b"],
+            "address": {"city": "leeds", "zip": "ls1"}, "dynamic": 3.0
+        }));
+
+        let mut out = Map::new();
+        crate::map_add!(out, "a", 1);
+        assert_eq!(crate::json_map!{}, Map::new());
+        assert_eq!(crate::json_map!{ ..out }, out);
+
+        let user = json!({"nick": " sam "});
+        let trimmed = crate::json_map!{ "nick" => user["nick"].as_str()?.trim(), "none" => user["x"].as_str()? };
+        assert_eq!(Value::Object(trimmed), json!({"nick": "sam"}));
+
+        let large = crate::json_map!{
+            "k0" => 0, "k1" => 1, "k2" => 2, "k3" => 3, "k4" => 4, "k5" => 5, "k6" => 6, "k7" => 7, "k8" => 8, "k9" => 9,
+            "k10" => 10, "k11" => 11, "k12" => 12, "k13" => 13, "k14" => 14, "k15" => 15, "k16" => 16, "k17" => 17, "k18" => 18, "k19" => 19,
+            "k20" => 20, "k21" => 21, "k22" => 22, "k23" => 23, "k24" => 24, "k25" => 25, "k26" => 26, "k27" => 27, "k28" => 28, "k29" => 29,
+            "k30" => 30, "k31" => 31, "k32" => 32, "k33" => 33, "k34" => 34, "k35" => 35, "k36" => 36, "k37" => 37, "k38" => 38, "k39" => 39,
+            "k40" => 40, "k41" => 41, "k42" => 42, "k43" => 43, "k44" => 44, "k45" => 45, "k46" => 46, "k47" => 47, "k48" => 48, "k49" => 49,
+            "k50" => 50, "k51" => 51, "k52" => 52, "k53" => 53, "k54" => 54, "k55" => 55, "k56" => 56, "k57" => 57, "k58" => 58, "k59" => 59,
+            "k60" => vec![1, 2, 3].len(), "k61" => format!("{}-{}", "a", "b"), "k62" => 62, "k63" => 63, "k64" => 64, "k65" => 65, "k66" => 66, "k67" => 67, "k68" => 68, "k69" => 69,
+            "k70" => 70, "k71" => 71, "k72" => 72, "k73" => 73, "k74" => 74, "k75" => 75, "k76" => 76, "k77" => 77, "k78" => 78, "k79" => 79,
+            "k80" => 80, "k81" => 81, "k82" => 82, "k83" => 83, "k84" => 84, "k85" => 85, "k86" => 86, "k87" => 87, "k88" => 88, "k89" => 89,
+            "k90" => 90, "k91" => 91, "k92" => 92, "k93" => 93, "k94" => 94, "k95" => 95, "k96" => 96, "k97" => 97, "k98" => 98, "k99" => 99,
+        };
+        assert_eq!(large.len(), 100);
+        assert_eq!(large["k61"], json!("a-b"));
+
+    }
+
+}
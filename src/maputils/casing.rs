@@ -0,0 +1,188 @@
+use serde_json::{Map, Value};
+
+use crate::serdeutils::{PathSegment, format_path};
+
+
+// recursive key renaming between the naming conventions used by front ends (camelCase) and the
+// services behind them (snake_case). keys in the exclusion list keep their name and their value is
+// left untouched, useful for free form metadata objects...
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    Snake,
+    Camel,
+    Pascal,
+    Kebab,
+}
+
+
+// splits "fooBar", "foo_bar", "foo-bar", "HTTPServer" and "Foo Bar" into words...
+fn split_words(stringin: &str) -> Vec<String> {
+
+    let chars: Vec<char> = stringin.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+
+        if c == '_' || c == '-' || c.is_whitespace() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && !current.is_empty() {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if previous.is_lowercase() || previous.is_ascii_digit() || (previous.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.push(c);
+
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+
+}
+
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+
+pub fn convert_case(stringin: &str, case: KeyCase) -> String {
+
+    // leading underscores are meaningful ("_id"), keep them...
+    let trimmed = stringin.trim_start_matches('_');
+    let prefix = &stringin[..stringin.len() - trimmed.len()];
+
+    let words = split_words(trimmed);
+
+    let body = match case {
+        KeyCase::Snake => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+        KeyCase::Kebab => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("-"),
+        KeyCase::Pascal => words.iter().map(|w| capitalize(w)).collect(),
+        KeyCase::Camel => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalize(w) })
+            .collect(),
+    };
+
+    format!("{prefix}{body}")
+
+}
+
+
+// when several keys convert to the same name, a key already spelled in the target case (or
+// excluded) wins, otherwise the first key in map order does. the losing keys are dropped and their
+// paths returned, so {"firstName": 1, "first_name": 2} becomes {"first_name": 2} and reports "firstName"...
+pub fn convert_keys(value: &mut Value, case: KeyCase, exclude: &[&str]) -> Vec<String> {
+    let mut dropped = Vec::new();
+    convert_value(value, case, exclude, &mut Vec::new(), &mut dropped);
+    dropped
+}
+
+
+pub fn convert_map_keys(map: &Map<String, Value>, case: KeyCase, exclude: &[&str]) -> (Map<String, Value>, Vec<String>) {
+    let mut dropped = Vec::new();
+    let out = convert_map(map.clone(), case, exclude, &mut Vec::new(), &mut dropped);
+    (out, dropped)
+}
+
+
+fn convert_value(value: &mut Value, case: KeyCase, exclude: &[&str], path: &mut Vec<PathSegment>, dropped: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            *map = convert_map(std::mem::take(map), case, exclude, path, dropped);
+        },
+        Value::Array(values) => {
+            for (index, item) in values.iter_mut().enumerate() {
+                path.push(PathSegment::Index(index));
+                convert_value(item, case, exclude, path, dropped);
+                path.pop();
+            }
+        },
+        _ => {}
+    }
+}
+
+
+fn convert_map(map: Map<String, Value>, case: KeyCase, exclude: &[&str], path: &mut Vec<PathSegment>, dropped: &mut Vec<String>) -> Map<String, Value> {
+
+    let target = |key: &str| if exclude.contains(&key) { key.to_string() } else { convert_case(key, case) };
+
+    // keys that keep their name go first so they win any collision...
+    let (kept, renamed): (Vec<_>, Vec<_>) = map.into_iter().partition(|(key, _)| target(key) == *key);
+
+    let mut out = Map::with_capacity(kept.len() + renamed.len());
+
+    for (key, mut value) in kept.into_iter().chain(renamed) {
+        let excluded = exclude.contains(&key.as_str());
+        let name = target(&key);
+        path.push(PathSegment::Key(key));
+        if out.contains_key(&name) {
+            dropped.push(format_path(path));
+        } else {
+            if !excluded {
+                convert_value(&mut value, case, exclude, path, dropped);
+            }
+            out.insert(name, value);
+        }
+        path.pop();
+    }
+
+    out
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{KeyCase, convert_case, convert_keys, convert_map_keys};
+
+    #[test]
+    fn casingtests() {
+
+        assert_eq!(convert_case("firstName", KeyCase::Snake), "first_name");
+        assert_eq!(convert_case("HTTPServerURL", KeyCase::Snake), "http_server_url");
+        assert_eq!(convert_case("address_line2", KeyCase::Camel), "addressLine2");
+        assert_eq!(convert_case("user-id", KeyCase::Pascal), "UserId");
+        assert_eq!(convert_case("UserId", KeyCase::Kebab), "user-id");
+        assert_eq!(convert_case("_createdAt", KeyCase::Snake), "_created_at");
+
+        let mut body = json!({"firstName": "sam", "homeAddress": {"postCode": "n1"}, "items": [{"itemId": 1}], "metaData": {"keepMe": 1}});
+        assert!(convert_keys(&mut body, KeyCase::Snake, &["metaData"]).is_empty());
+
+        assert_eq!(body, json!({"first_name": "sam", "home_address": {"post_code": "n1"}, "items": [{"item_id": 1}], "metaData": {"keepMe": 1}}));
+
+        let mut clash = json!({"firstName": 1, "first_name": 2, "rows": [{"a_b": 1, "aB": 2, "a-b": 3}]});
+        let dropped = convert_keys(&mut clash, KeyCase::Snake, &[]);
+        assert_eq!(clash, json!({"first_name": 2, "rows": [{"a_b": 1}]}));
+        assert_eq!(dropped, vec!["rows[0].a-b", "rows[0].aB", "firstName"]);
+
+        let map = serde_json::from_value(json!({"userId": 1, "UserId": 2})).unwrap();
+        let (converted, dropped) = convert_map_keys(&map, KeyCase::Camel, &[]);
+        assert_eq!(serde_json::Value::Object(converted), json!({"userId": 1}));
+        assert_eq!(dropped, vec!["UserId"]);
+
+    }
+
+}
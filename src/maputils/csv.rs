@@ -0,0 +1,568 @@
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::{Map, Value};
+
+use crate::serdeutils::{FlattenOptions, PathError, flatten_with, unflatten_with};
+
+
+// csv import / export for json records. nested values are flattened to "a.b[0]" style columns on
+// export and optionally rebuilt on import. the reader and writer stream row by row, to_csv /
+// from_csv are the in memory shortcuts...
+
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub columns: Option<Vec<String>>,
+    pub infer_types: bool,
+    pub max_record_size: usize,
+    pub nested: bool,
+    pub flatten: FlattenOptions,
+}
+
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self { delimiter: ',', columns: None, infer_types: true, max_record_size: 1 << 20, nested: true, flatten: FlattenOptions::default() }
+    }
+}
+
+
+impl CsvOptions {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    // selects and orders columns, on export they form the header and on import other columns are dropped...
+    pub fn columns<'a>(mut self, columns: impl IntoIterator<Item = &'a str>) -> Self {
+        self.columns = Some(columns.into_iter().map(str::to_string).collect());
+        self
+    }
+
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.infer_types = infer;
+        self
+    }
+
+    // upper bound in bytes for one record, guards against an unterminated quote swallowing the file...
+    pub fn max_record_size(mut self, bytes: usize) -> Self {
+        self.max_record_size = bytes;
+        self
+    }
+
+    // when false, dotted headers are kept as flat keys on import and nested values are written as json text...
+    pub fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    pub fn flatten(mut self, flatten: FlattenOptions) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+}
+
+
+#[derive(Debug)]
+pub enum CsvErrorKind {
+    Io(io::Error),
+    UnterminatedQuote,
+    RecordTooLarge { limit: usize },
+    FieldCount { expected: usize, found: usize },
+    Path(PathError),
+}
+
+
+#[derive(Debug)]
+pub struct CsvError {
+    pub line: usize,
+    pub kind: CsvErrorKind,
+}
+
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CsvErrorKind::Io(e) => write!(f, "line {}: read error: {e}", self.line),
+            CsvErrorKind::UnterminatedQuote => write!(f, "line {}: unterminated quoted field", self.line),
+            CsvErrorKind::RecordTooLarge { limit } => write!(f, "line {}: record is larger than {limit} bytes", self.line),
+            CsvErrorKind::FieldCount { expected, found } => write!(f, "line {}: expected {expected} fields, found {found}", self.line),
+            CsvErrorKind::Path(e) => write!(f, "line {}: {e}", self.line),
+        }
+    }
+}
+
+
+impl std::error::Error for CsvError {}
+
+
+pub fn quote_field(field: &str, delimiter: char) -> String {
+    let needs_quotes = field.contains(delimiter)
+        || field.contains(['"', '\n', '\r'])
+        || field.starts_with(char::is_whitespace)
+        || field.ends_with(char::is_whitespace);
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+
+// leading zeros stay text ("007"), the reader only infers unquoted cells so a quoted "12" is kept as written...
+pub fn infer_value(text: &str) -> Value {
+
+    if text.is_empty() {
+        return Value::Null;
+    }
+
+    match text {
+        "true" | "TRUE" | "True" => return Value::Bool(true),
+        "false" | "FALSE" | "False" => return Value::Bool(false),
+        _ => {},
+    }
+
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    if leading_zero || !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return Value::String(text.to_string());
+    }
+
+    if let Ok(n) = text.parse::<i64>() {
+        return Value::from(n);
+    }
+    if let Ok(n) = text.parse::<u64>() {
+        return Value::from(n);
+    }
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => Value::from(n),
+        _ => Value::String(text.to_string()),
+    }
+
+}
+
+
+// (field, was_quoted) pairs for one record...
+type RawRecord = Vec<(String, bool)>;
+
+
+// tracks split_record's quoting state across the lines of one record, so a record spanning many
+// lines is scanned once instead of re-split on every line...
+#[derive(Default)]
+struct QuoteScan {
+    in_quotes: bool,
+    quoted: bool,
+    started: bool,
+}
+
+
+impl QuoteScan {
+
+    fn feed(&mut self, text: &str, delimiter: char) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if self.in_quotes {
+                if c != '"' {
+                    self.started = true;
+                } else if chars.peek() == Some(&'"') {
+                    chars.next();
+                    self.started = true;
+                } else {
+                    self.in_quotes = false;
+                }
+            } else if c == '"' && !self.started && !self.quoted {
+                self.quoted = true;
+                self.in_quotes = true;
+            } else if c == delimiter {
+                self.quoted = false;
+                self.started = false;
+            } else {
+                self.started = true;
+            }
+        }
+    }
+
+}
+
+
+// None when a quoted field runs past the end of the text...
+fn split_record(text: &str, delimiter: char) -> Option<RawRecord> {
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.is_empty() && !quoted {
+            quoted = true;
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push((std::mem::take(&mut field), quoted));
+            quoted = false;
+        } else {
+            field.push(c);
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+
+    fields.push((field, quoted));
+    Some(fields)
+
+}
+
+
+pub struct CsvReader<R: BufRead> {
+    reader: R,
+    options: CsvOptions,
+    header: Option<Vec<String>>,
+    line: usize,
+    stopped: bool,
+}
+
+
+impl <R: BufRead> CsvReader<R> {
+
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, CsvOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: CsvOptions) -> Self {
+        Self { reader, options, header: None, line: 0, stopped: false }
+    }
+
+    // the header row, available once the first record has been read...
+    pub fn header(&self) -> Option<&[String]> {
+        self.header.as_deref()
+    }
+
+    // reads one logical record, which may span lines inside quotes. returns the starting line.
+    // io errors and oversized records stop the reader as the position in the file is lost...
+    fn read_record(&mut self) -> Option<Result<(usize, RawRecord), CsvError>> {
+
+        let mut buffer = String::new();
+
+        loop {
+
+            let start = self.line + 1;
+            let mut scan = QuoteScan::default();
+            buffer.clear();
+
+            loop {
+                self.line += 1;
+                let offset = buffer.len();
+                let limit = self.options.max_record_size;
+                // never buffer more than one byte past the limit, even for a line without a newline...
+                let mut line = Vec::new();
+                let read = (&mut self.reader).take((limit.saturating_sub(offset) + 1) as u64).read_until(b'\n', &mut line);
+                let read = read.and_then(|n| {
+                    if offset + line.len() > limit {
+                        return Ok(n);
+                    }
+                    let text = std::str::from_utf8(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    buffer.push_str(text);
+                    Ok(n)
+                });
+                match read {
+                    Ok(0) if buffer.is_empty() => return None,
+                    Ok(0) => return Some(Err(CsvError { line: start, kind: CsvErrorKind::UnterminatedQuote })),
+                    Ok(_) if offset + line.len() > limit => {
+                        self.stopped = true;
+                        return Some(Err(CsvError { line: start, kind: CsvErrorKind::RecordTooLarge { limit } }));
+                    },
+                    Ok(_) => {
+                        scan.feed(&buffer[offset..], self.options.delimiter);
+                        if scan.in_quotes {
+                            continue;
+                        }
+                        let text = buffer.trim_end_matches(['\n', '\r']);
+                        if text.is_empty() {
+                            break;
+                        }
+                        return Some(split_record(text, self.options.delimiter)
+                            .map(|fields| (start, fields))
+                            .ok_or(CsvError { line: start, kind: CsvErrorKind::UnterminatedQuote }));
+                    },
+                    Err(e) => {
+                        self.stopped = true;
+                        return Some(Err(CsvError { line: self.line, kind: CsvErrorKind::Io(e) }));
+                    },
+                }
+            }
+
+        }
+
+    }
+
+    fn build(&self, line: usize, fields: RawRecord) -> Result<Map<String, Value>, CsvError> {
+
+        let header = self.header.as_deref().unwrap_or_default();
+        if fields.len() != header.len() {
+            return Err(CsvError { line, kind: CsvErrorKind::FieldCount { expected: header.len(), found: fields.len() } });
+        }
+
+        let mut flat = Map::with_capacity(header.len());
+        for (name, (text, quoted)) in header.iter().zip(fields) {
+            if let Some(columns) = &self.options.columns
+                && !columns.contains(name) {
+                    continue;
+                }
+            let value = if self.options.infer_types && !quoted { infer_value(&text) } else { Value::String(text) };
+            flat.insert(name.clone(), value);
+        }
+
+        if let Some(columns) = &self.options.columns {
+            flat = columns.iter().filter_map(|c| flat.remove(c).map(|v| (c.clone(), v))).collect();
+        }
+
+        if !self.options.nested {
+            return Ok(flat);
+        }
+
+        match unflatten_with(&flat, &self.options.flatten) {
+            Ok(Value::Object(map)) => Ok(map),
+            Ok(_) => Ok(Map::new()),
+            Err(e) => Err(CsvError { line, kind: CsvErrorKind::Path(e) }),
+        }
+
+    }
+
+}
+
+
+impl <R: BufRead> Iterator for CsvReader<R> {
+
+    type Item = Result<(usize, Map<String, Value>), CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.stopped {
+            return None;
+        }
+
+        if self.header.is_none() {
+            match self.read_record()? {
+                Ok((_, fields)) => self.header = Some(fields.into_iter().map(|(name, _)| name.trim().to_string()).collect()),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        Some(self.read_record()?.and_then(|(line, fields)| self.build(line, fields).map(|record| (line, record))))
+
+    }
+
+}
+
+
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    options: CsvOptions,
+    header: Option<Vec<String>>,
+    count: usize,
+}
+
+
+impl <W: Write> CsvWriter<W> {
+
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, CsvOptions::default())
+    }
+
+    // without explicit columns the header comes from the first record written...
+    pub fn with_options(writer: W, options: CsvOptions) -> Self {
+        Self { writer, options, header: None, count: 0 }
+    }
+
+    fn write_row<'a>(&mut self, fields: impl IntoIterator<Item = &'a str>) -> io::Result<()> {
+        let delimiter = self.options.delimiter.to_string();
+        let row: Vec<String> = fields.into_iter().map(|f| quote_field(f, self.options.delimiter)).collect();
+        self.writer.write_all(row.join(&delimiter).as_bytes())?;
+        self.writer.write_all(b"\r\n")
+    }
+
+    fn cells(&self, record: &Map<String, Value>) -> Map<String, Value> {
+        if self.options.nested {
+            flatten_with(&Value::Object(record.clone()), &self.options.flatten)
+        } else {
+            record.clone()
+        }
+    }
+
+    pub fn write_header(&mut self, columns: &[String]) -> io::Result<()> {
+        self.write_row(columns.iter().map(String::as_str))?;
+        self.header = Some(columns.to_vec());
+        Ok(())
+    }
+
+    pub fn write(&mut self, record: &Map<String, Value>) -> io::Result<()> {
+
+        let cells = self.cells(record);
+
+        if self.header.is_none() {
+            let columns = self.options.columns.clone().unwrap_or_else(|| cells.keys().cloned().collect());
+            self.write_header(&columns)?;
+        }
+
+        let row: Vec<String> = self.header.iter().flatten().map(|column| cell_text(cells.get(column))).collect();
+        self.write_row(row.iter().map(String::as_str))?;
+        self.count += 1;
+        Ok(())
+
+    }
+
+    pub fn write_all<'a>(&mut self, records: impl IntoIterator<Item = &'a Map<String, Value>>) -> io::Result<()> {
+        for record in records {
+            self.write(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+}
+
+
+// unlike the streaming writer, the header is the union of every record's columns in first seen order...
+pub fn to_csv(records: &[Map<String, Value>], options: &CsvOptions) -> io::Result<String> {
+
+    let mut writer = CsvWriter::with_options(Vec::new(), options.clone());
+
+    let columns = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => {
+            let mut columns: Vec<String> = Vec::new();
+            for record in records {
+                for key in writer.cells(record).keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            columns
+        },
+    };
+
+    writer.write_header(&columns)?;
+    writer.write_all(records)?;
+    String::from_utf8(writer.finish()?).map_err(io::Error::other)
+
+}
+
+
+pub fn from_csv(text: &str, options: &CsvOptions) -> Result<Vec<Map<String, Value>>, CsvError> {
+    CsvReader::with_options(text.as_bytes(), options.clone()).map(|row| row.map(|(_, record)| record)).collect()
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Map, Value, json};
+
+    use super::{CsvErrorKind, CsvOptions, CsvReader, CsvWriter, from_csv, infer_value, to_csv};
+
+    fn records(value: Value) -> Vec<Map<String, Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn csvtests() {
+
+        let rows = records(json!([
+            {"id": 1, "name": "Smith, Sam", "address": {"city": "leeds"}, "tags": ["a", "b"]},
+            {"id": 2, "name": "say \"hi\"", "note": "two\nlines", "zip": "007"}
+        ]));
+
+        let text = to_csv(&rows, &CsvOptions::new()).unwrap();
+        assert_eq!(text.lines().next().unwrap(), "address.city,id,name,tags[0],tags[1],note,zip");
+        assert!(text.contains("\"Smith, Sam\""));
+        assert!(text.contains("\"say \"\"hi\"\"\""));
+
+        let back = from_csv(&text, &CsvOptions::new()).unwrap();
+        assert_eq!(back[0]["address"], json!({"city": "leeds"}));
+        assert_eq!(back[0]["tags"], json!(["a", "b"]));
+        assert_eq!(back[1]["note"], json!("two\nlines"));
+        assert_eq!(back[1]["zip"], json!("007"));
+        assert_eq!(back[1]["id"], json!(2));
+
+        let picked = to_csv(&rows, &CsvOptions::new().columns(["name", "id"]).delimiter(';')).unwrap();
+        assert_eq!(picked, "name;id\r\nSmith, Sam;1\r\n\"say \"\"hi\"\"\";2\r\n");
+
+        let input = "id,score,flag,label\n1,2.5,true,\"12\"\n2,x\n\n3,,false,ok\n";
+        let results: Vec<_> = CsvReader::new(input.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(Value::Object(results[0].as_ref().unwrap().1.clone()), json!({"id": 1, "score": 2.5, "flag": true, "label": "12"}));
+        assert!(matches!(results[1], Err(ref e) if e.line == 3 && matches!(e.kind, CsvErrorKind::FieldCount { expected: 4, found: 2 })));
+        assert_eq!(results[2].as_ref().unwrap().0, 5);
+        assert_eq!(results[2].as_ref().unwrap().1["score"], Value::Null);
+
+        let selected = from_csv(input.lines().take(2).collect::<Vec<_>>().join("\n").as_str(), &CsvOptions::new().columns(["label", "id"]).infer_types(false)).unwrap();
+        assert_eq!(Value::Object(selected[0].clone()), json!({"label": "12", "id": "1"}));
+        assert!(matches!(from_csv("a\n\"open", &CsvOptions::new()).unwrap_err().kind, CsvErrorKind::UnterminatedQuote));
+
+        let runaway = format!("a,b\n\"open,1\n{}", "x,y\n".repeat(1000));
+        let results: Vec<_> = CsvReader::with_options(runaway.as_bytes(), CsvOptions::new().max_record_size(256)).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(ref e) if e.line == 2 && matches!(e.kind, CsvErrorKind::RecordTooLarge { limit: 256 })));
+        let endless = std::io::BufReader::new(std::io::repeat(b'x'));
+        let mut reader = CsvReader::with_options(endless, CsvOptions::new().max_record_size(256));
+        assert!(matches!(reader.next(), Some(Err(ref e)) if e.line == 1 && matches!(e.kind, CsvErrorKind::RecordTooLarge { limit: 256 })));
+        assert!(reader.next().is_none());
+        assert_eq!(from_csv("a,b\n5\" screen,\"x\"\"y\"\n", &CsvOptions::new()).unwrap()[0]["b"], json!("x\"y"));
+
+        let huge = from_csv("id,a[18446744073709551614]\n1,2\n", &CsvOptions::new()).unwrap_err();
+        assert!(matches!(huge.kind, CsvErrorKind::Path(_)));
+
+        assert_eq!(infer_value("-0.5"), json!(-0.5));
+        assert_eq!(infer_value("1e3"), json!(1000.0));
+        assert_eq!(infer_value("inf"), json!("inf"));
+
+        let mut writer = CsvWriter::new(Vec::new());
+        writer.write_all(&rows).unwrap();
+        assert_eq!(writer.count(), 2);
+        let streamed = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(streamed.ends_with(",2,\"say \"\"hi\"\"\",,\r\n"));
+
+    }
+
+}
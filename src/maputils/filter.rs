@@ -0,0 +1,499 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use super::map_lookup;
+use crate::serdeutils::{FromLenient, parse_path};
+
+
+// segment filters over json records, compiled once and evaluated per row...
+//
+//   status == "active" && age >= 18 && tags contains "vip"
+//   !(plan in ["free", "trial"]) || meta.flags[0] == true
+//   email matches "@example\.com$"
+//   verified                         truthy check on a field
+//
+// fields are read with the same lenient coercions as val_to* so "18" >= 18, and keys may be
+// paths. comparisons: == != < <= > >= contains in matches, combined with && || ! and grouping.
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    pub expression: String,
+    pub position: usize,
+    pub message: &'static str,
+}
+
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in '{}' at position {}", self.message, self.expression, self.position)
+    }
+}
+
+
+impl std::error::Error for FilterError {}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    In,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Field(String),
+    Literal(Value),
+}
+
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Truthy(Operand),
+    Compare(Operand, CompareOp, Operand),
+    Matches(Operand, Regex),
+    // && / || chains are kept flat so long chains don't build deep trees...
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expression: String,
+    expr: Expr,
+}
+
+
+impl Filter {
+
+    pub fn compile(expression: &str) -> Result<Self, FilterError> {
+        let mut parser = Parser { src: expression, pos: 0, depth: 0 };
+        let expr = parser.parse_or()?;
+        parser.skip_ws();
+        if parser.pos < expression.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(Self { expression: expression.to_string(), expr })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn matches(&self, record: &Map<String, Value>) -> bool {
+        eval(&self.expr, record)
+    }
+
+    pub fn apply(&self, records: &[Map<String, Value>]) -> Vec<Map<String, Value>> {
+        records.iter().filter(|r| self.matches(r)).cloned().collect()
+    }
+
+}
+
+
+impl FromStr for Filter {
+
+    type Err = FilterError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::compile(expression)
+    }
+
+}
+
+
+fn resolve<'a>(operand: &'a Operand, record: &'a Map<String, Value>) -> Option<&'a Value> {
+    match operand {
+        Operand::Field(key) => map_lookup(record, key).filter(|v| !v.is_null()),
+        Operand::Literal(Value::Null) => None,
+        Operand::Literal(value) => Some(value),
+    }
+}
+
+
+// numbers win over bools over text, so either side may be a string holding the value...
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+
+    if (left.is_number() || right.is_number())
+        && let (Ok(l), Ok(r)) = (f64::from_lenient(left), f64::from_lenient(right)) {
+            return l.partial_cmp(&r);
+        }
+
+    if (left.is_boolean() || right.is_boolean())
+        && let (Ok(l), Ok(r)) = (bool::from_lenient(left), bool::from_lenient(right)) {
+            return Some(l.cmp(&r));
+        }
+
+    match (String::from_lenient(left), String::from_lenient(right)) {
+        (Ok(l), Ok(r)) => Some(l.cmp(&r)),
+        _ => if left == right { Some(Ordering::Equal) } else { None },
+    }
+
+}
+
+
+fn lenient_eq(left: &Value, right: &Value) -> bool {
+    compare_values(left, right) == Some(Ordering::Equal)
+}
+
+
+fn eval(expr: &Expr, record: &Map<String, Value>) -> bool {
+
+    match expr {
+
+        Expr::Truthy(operand) => match resolve(operand, record) {
+            Some(value) => bool::from_lenient(value).unwrap_or(true),
+            None => false,
+        },
+
+        Expr::Compare(left, op, right) => {
+            let left = resolve(left, record);
+            let right = resolve(right, record);
+            match (op, left, right) {
+                (CompareOp::Contains, Some(Value::Array(items)), Some(r)) => items.iter().any(|item| lenient_eq(item, r)),
+                (CompareOp::Contains, Some(Value::String(s)), Some(r)) => String::from_lenient(r).is_ok_and(|r| s.contains(&r)),
+                (CompareOp::Contains, _, _) => false,
+                (CompareOp::In, Some(l), Some(Value::Array(items))) => items.iter().any(|item| lenient_eq(l, item)),
+                (CompareOp::In, _, _) => false,
+                (_, Some(l), Some(r)) => {
+                    let ordering = compare_values(l, r);
+                    match op {
+                        CompareOp::Eq => ordering == Some(Ordering::Equal),
+                        CompareOp::Ne => ordering != Some(Ordering::Equal),
+                        CompareOp::Lt => ordering == Some(Ordering::Less),
+                        CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                        CompareOp::Gt => ordering == Some(Ordering::Greater),
+                        CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                        CompareOp::Contains | CompareOp::In => false,
+                    }
+                },
+                // a missing field only equals null...
+                (_, None, None) => *op == CompareOp::Eq,
+                _ => *op == CompareOp::Ne,
+            }
+        },
+
+        Expr::Matches(operand, pattern) => resolve(operand, record)
+            .and_then(|v| String::from_lenient(v).ok())
+            .is_some_and(|s| pattern.is_match(&s)),
+
+        Expr::And(terms) => terms.iter().all(|term| eval(term, record)),
+        Expr::Or(terms) => terms.iter().any(|term| eval(term, record)),
+        Expr::Not(inner) => !eval(inner, record),
+
+    }
+
+}
+
+
+// deepest nesting of ! and ( ) accepted, keeps hostile input from overflowing the stack...
+const MAX_DEPTH: usize = 128;
+
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+
+impl Parser<'_> {
+
+    fn error(&self, message: &'static str) -> FilterError {
+        FilterError { expression: self.src.to_string(), position: self.pos, message }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.src[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    // keywords must not run on into an identifier, "index" is a field and not "in"...
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let rest = &self.src[self.pos..];
+        rest.strip_prefix(keyword).is_some_and(|after| after.chars().next().is_none_or(|c| !is_ident(c))) && self.eat(keyword)
+    }
+
+    fn expect(&mut self, token: &str, message: &'static str) -> Result<(), FilterError> {
+        self.skip_ws();
+        if self.eat(token) { Ok(()) } else { Err(self.error(message)) }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FilterError> {
+        let mut terms = vec![self.parse_and()?];
+        loop {
+            self.skip_ws();
+            if !self.eat("||") {
+                return Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) });
+            }
+            terms.push(self.parse_and()?);
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FilterError> {
+        let mut terms = vec![self.parse_unary()?];
+        loop {
+            self.skip_ws();
+            if !self.eat("&&") {
+                return Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::And(terms) });
+            }
+            terms.push(self.parse_unary()?);
+        }
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, FilterError>) -> Result<T, FilterError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("expression is nested too deeply"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, FilterError> {
+        self.skip_ws();
+        if self.peek() == Some('!') && !self.src[self.pos..].starts_with("!=") {
+            self.pos += 1;
+            return self.nested(|p| Ok(Expr::Not(Box::new(p.parse_unary()?))));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, FilterError> {
+
+        self.skip_ws();
+
+        if self.eat("(") {
+            return self.nested(|p| {
+                let inner = p.parse_or()?;
+                p.expect(")", "expected ')'")?;
+                Ok(inner)
+            });
+        }
+
+        let left = self.parse_operand()?;
+        self.skip_ws();
+
+        if self.eat_keyword("matches") {
+            self.skip_ws();
+            let start = self.pos;
+            let Operand::Literal(Value::String(pattern)) = self.parse_operand()? else {
+                return Err(FilterError { expression: self.src.to_string(), position: start, message: "expected a pattern string" });
+            };
+            let regex = Regex::new(&pattern)
+                .map_err(|_| FilterError { expression: self.src.to_string(), position: start, message: "invalid pattern" })?;
+            return Ok(Expr::Matches(left, regex));
+        }
+
+        let op = if self.eat("==") {
+            CompareOp::Eq
+        } else if self.eat("!=") {
+            CompareOp::Ne
+        } else if self.eat("<=") {
+            CompareOp::Le
+        } else if self.eat(">=") {
+            CompareOp::Ge
+        } else if self.eat("<") {
+            CompareOp::Lt
+        } else if self.eat(">") {
+            CompareOp::Gt
+        } else if self.eat_keyword("contains") {
+            CompareOp::Contains
+        } else if self.eat_keyword("in") {
+            CompareOp::In
+        } else {
+            return Ok(Expr::Truthy(left));
+        };
+
+        Ok(Expr::Compare(left, op, self.parse_operand()?))
+
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, FilterError> {
+        let quote = self.bump().ok_or_else(|| self.error("expected a quoted string"))?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some(c) => out.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) if c == quote => return Ok(out),
+                Some(c) => out.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Value, FilterError> {
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.eat("]") {
+            return Ok(Value::Array(items));
+        }
+        loop {
+            match self.parse_operand()? {
+                Operand::Literal(value) => items.push(value),
+                Operand::Field(_) => return Err(self.error("lists may only hold literals")),
+            }
+            self.skip_ws();
+            if self.eat("]") {
+                return Ok(Value::Array(items));
+            }
+            self.expect(",", "expected ',' or ']'")?;
+        }
+    }
+
+    // field paths run until whitespace or an operator, bracketed segments may hold anything...
+    fn parse_field(&mut self) -> Result<Operand, FilterError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == '[' {
+                let quoted = self.src[self.pos..].starts_with("[\"") || self.src[self.pos..].starts_with("['");
+                self.bump();
+                if quoted {
+                    self.parse_quoted()?;
+                }
+                while self.peek().is_some_and(|c| c != ']') {
+                    self.bump();
+                }
+                self.expect("]", "expected ']'")?;
+            } else if is_ident(c) || c == '.' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        let key = &self.src[start..self.pos];
+        if parse_path(key).is_err() {
+            return Err(FilterError { expression: self.src.to_string(), position: start, message: "invalid field path" });
+        }
+        Ok(Operand::Field(key.to_string()))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, FilterError> {
+
+        self.skip_ws();
+
+        match self.peek() {
+            Some('\'' | '"') => Ok(Operand::Literal(Value::String(self.parse_quoted()?))),
+            Some('[') => {
+                self.pos += 1;
+                Ok(Operand::Literal(self.parse_list()?))
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || "-+.eE".contains(c)) {
+                    self.pos += 1;
+                }
+                serde_json::from_str::<Value>(&self.src[start..self.pos])
+                    .ok()
+                    .filter(Value::is_number)
+                    .map(Operand::Literal)
+                    .ok_or_else(|| self.error("invalid number"))
+            },
+            Some(c) if is_ident(c) => {
+                if self.eat_keyword("true") {
+                    Ok(Operand::Literal(Value::Bool(true)))
+                } else if self.eat_keyword("false") {
+                    Ok(Operand::Literal(Value::Bool(false)))
+                } else if self.eat_keyword("null") {
+                    Ok(Operand::Literal(Value::Null))
+                } else {
+                    self.parse_field()
+                }
+            },
+            _ => Err(self.error("expected a field or a literal")),
+        }
+
+    }
+
+}
+
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Map, Value, json};
+
+    use super::Filter;
+
+    #[test]
+    fn filtertests() {
+
+        let record: Map<String, Value> = serde_json::from_value(json!({
+            "status": "active", "age": "21", "tags": ["new", "vip"], "verified": 1,
+            "email": "sam@example.com", "meta": {"limits": [{"max": 5}]}, "index": 3
+        })).unwrap();
+
+        let passes = |expression: &str| Filter::compile(expression).unwrap().matches(&record);
+
+        assert!(passes(r#"status == "active" && age >= 18 && tags contains "vip""#));
+        assert!(passes("verified && meta.limits[0].max < 10"));
+        assert!(passes(r#"!(status in ["banned", "closed"]) || age < 0"#));
+        assert!(passes(r#"email matches "@example\\.com$""#));
+        assert!(passes("missing == null && missing != 1 && index in [1, 2, 3]"));
+        assert!(!passes(r#"tags contains "gold" || status != "active""#));
+        assert!(!passes("missing"));
+
+        let error = Filter::compile("age >= && status").unwrap_err();
+        assert_eq!((error.position, error.message), (7, "expected a field or a literal"));
+        assert!("status ==".parse::<Filter>().is_err());
+        assert!("(age > 1".parse::<Filter>().is_err());
+        assert!(r#"email matches "(""#.parse::<Filter>().is_err());
+
+        let deep = format!("{}a", "!".repeat(200000));
+        assert_eq!(Filter::compile(&deep).unwrap_err().message, "expression is nested too deeply");
+        assert!(Filter::compile(&format!("{}a{}", "(".repeat(200), ")".repeat(200))).is_err());
+        assert!(passes(&format!("{}status{}", "(".repeat(100), ")".repeat(100))));
+
+        let chain = format!("{}status", "status && ".repeat(200000));
+        assert!(passes(&chain));
+        assert!(passes(&format!("{}status", "missing || ".repeat(200000))));
+
+        let rows = vec![record.clone(), Map::new()];
+        assert_eq!(Filter::compile("age > 20").unwrap().apply(&rows).len(), 1);
+
+    }
+
+}
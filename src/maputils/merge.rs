@@ -0,0 +1,226 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::serdeutils::{PathSegment, format_path};
+
+
+// layered configuration merges (defaults <- tenant <- user). objects are always merged
+// recursively, the strategy decides what happens when both sides hold different values...
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStrategy {
+    #[default]
+    Overwrite,
+    KeepLeft,
+    Error,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ArrayStrategy {
+    // arrays are treated like any other value and follow the conflict strategy...
+    #[default]
+    Conflict,
+    Concat,
+    Union,
+    // object items with the same value for the key are deep merged, the rest are unioned...
+    UnionByKey(String),
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeStrategy {
+    pub conflicts: ConflictStrategy,
+    pub arrays: ArrayStrategy,
+}
+
+
+impl MergeStrategy {
+
+    pub fn new(conflicts: ConflictStrategy, arrays: ArrayStrategy) -> Self {
+        Self { conflicts, arrays }
+    }
+
+    pub fn overwrite() -> Self {
+        Self::new(ConflictStrategy::Overwrite, ArrayStrategy::Conflict)
+    }
+
+    pub fn keep_left() -> Self {
+        Self::new(ConflictStrategy::KeepLeft, ArrayStrategy::Conflict)
+    }
+
+    pub fn strict() -> Self {
+        Self::new(ConflictStrategy::Error, ArrayStrategy::Conflict)
+    }
+
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeReport {
+    pub conflicts: Vec<String>,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeError {
+    pub conflicts: Vec<String>,
+}
+
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "merge conflict at {}", self.conflicts.join(", "))
+    }
+}
+
+
+impl std::error::Error for MergeError {}
+
+
+// merges right into left, with ConflictStrategy::Error left is untouched when anything conflicts...
+pub fn deep_merge(left: &mut Map<String, Value>, right: &Map<String, Value>, strategy: &MergeStrategy) -> Result<MergeReport, MergeError> {
+
+    let mut report = MergeReport::default();
+    let mut path = Vec::new();
+
+    if strategy.conflicts == ConflictStrategy::Error {
+        let mut working = left.clone();
+        merge_maps(&mut working, right, strategy, &mut path, &mut report);
+        if !report.conflicts.is_empty() {
+            return Err(MergeError { conflicts: report.conflicts });
+        }
+        *left = working;
+    } else {
+        merge_maps(left, right, strategy, &mut path, &mut report);
+    }
+
+    Ok(report)
+
+}
+
+
+// convenience for layering several maps in order, later layers win...
+pub fn deep_merge_all<'a>(layers: impl IntoIterator<Item = &'a Map<String, Value>>, strategy: &MergeStrategy) -> Result<Map<String, Value>, MergeError> {
+    let mut out = Map::new();
+    for layer in layers {
+        deep_merge(&mut out, layer, strategy)?;
+    }
+    Ok(out)
+}
+
+
+fn merge_maps(left: &mut Map<String, Value>, right: &Map<String, Value>, strategy: &MergeStrategy, path: &mut Vec<PathSegment>, report: &mut MergeReport) {
+    for (key, value) in right {
+        path.push(PathSegment::Key(key.clone()));
+        match left.get_mut(key) {
+            Some(existing) => merge_values(existing, value, strategy, path, report),
+            None => {
+                left.insert(key.clone(), value.clone());
+            },
+        }
+        path.pop();
+    }
+}
+
+
+fn merge_values(left: &mut Value, right: &Value, strategy: &MergeStrategy, path: &mut Vec<PathSegment>, report: &mut MergeReport) {
+
+    match (left, right) {
+
+        (Value::Object(l), Value::Object(r)) => merge_maps(l, r, strategy, path, report),
+
+        (Value::Array(l), Value::Array(r)) if strategy.arrays != ArrayStrategy::Conflict => {
+            match &strategy.arrays {
+                ArrayStrategy::Concat => l.extend(r.iter().cloned()),
+                ArrayStrategy::Union => {
+                    for item in r {
+                        if !l.contains(item) {
+                            l.push(item.clone());
+                        }
+                    }
+                },
+                ArrayStrategy::UnionByKey(key) => {
+                    for item in r {
+                        let id = item.get(key).filter(|id| !id.is_null());
+                        let position = id.and_then(|id| l.iter().position(|existing| existing.get(key) == Some(id)));
+                        match position {
+                            Some(index) => {
+                                path.push(PathSegment::Index(index));
+                                merge_values(&mut l[index], item, strategy, path, report);
+                                path.pop();
+                            },
+                            None if id.is_none() && l.contains(item) => {},
+                            None => l.push(item.clone()),
+                        }
+                    }
+                },
+                ArrayStrategy::Conflict => {},
+            }
+        },
+
+        (left, right) => {
+            if left == right {
+                return;
+            }
+            report.conflicts.push(format_path(path));
+            if strategy.conflicts == ConflictStrategy::Overwrite {
+                *left = right.clone();
+            }
+        },
+
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Map, Value, json};
+
+    use super::{ArrayStrategy, ConflictStrategy, MergeStrategy, deep_merge, deep_merge_all};
+
+    fn map(value: Value) -> Map<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn mergetests() {
+
+        let defaults = map(json!({"theme": {"color": "blue", "size": 12}, "tags": ["a"], "plans": [{"id": 1, "price": 10}]}));
+        let tenant = map(json!({"theme": {"color": "red"}, "tags": ["b", "a"], "plans": [{"id": 1, "price": 8}, {"id": 2, "price": 20}]}));
+
+        let mut out = defaults.clone();
+        let report = deep_merge(&mut out, &tenant, &MergeStrategy::overwrite()).unwrap();
+        assert_eq!(out["theme"], json!({"color": "red", "size": 12}));
+        assert_eq!(out["tags"], json!(["b", "a"]));
+        assert_eq!(report.conflicts, vec!["plans", "tags", "theme.color"]);
+
+        let mut out = defaults.clone();
+        deep_merge(&mut out, &tenant, &MergeStrategy::keep_left()).unwrap();
+        assert_eq!(out["theme"]["color"], json!("blue"));
+
+        let mut out = defaults.clone();
+        deep_merge(&mut out, &tenant, &MergeStrategy::new(ConflictStrategy::Overwrite, ArrayStrategy::UnionByKey("id".to_string()))).unwrap();
+        assert_eq!(out["plans"], json!([{"id": 1, "price": 8}, {"id": 2, "price": 20}]));
+        assert_eq!(out["tags"], json!(["a", "b"]));
+
+        let mut out = defaults.clone();
+        deep_merge(&mut out, &tenant, &MergeStrategy::new(ConflictStrategy::KeepLeft, ArrayStrategy::Concat)).unwrap();
+        assert_eq!(out["tags"], json!(["a", "b", "a"]));
+
+        let mut out = defaults.clone();
+        let error = deep_merge(&mut out, &tenant, &MergeStrategy::strict()).unwrap_err();
+        assert_eq!(error.conflicts.len(), 3);
+        assert_eq!(out, defaults);
+
+        let layered = deep_merge_all([&defaults, &map(json!({"theme": {"size": 14}}))], &MergeStrategy::default()).unwrap();
+        assert_eq!(layered["theme"], json!({"color": "blue", "size": 14}));
+
+    }
+
+}
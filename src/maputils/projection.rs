@@ -0,0 +1,192 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+use crate::serdeutils::{PathError, PathSegment, format_path, get_segments, parse_path, remove_segments, set_segments};
+
+
+// reusable shaping of documents, compile once and apply per request...
+//
+//   let v1 = Projection::new()
+//       .field("id as user_id")?
+//       .field("user.address.city")?
+//       .field_or("status", json!("active"))?
+//       .computed("display", |doc| json!(format!("{} ({})", val_tostr(doc, "name", ""), val_tou64(doc, "id", 0))))?;
+//
+//   let public = Projection::all().exclude("user.password")?;
+//
+// field specs are "source" or "source as target", both in the recurse_value path syntax. missing
+// sources without a default are skipped.
+
+
+type ComputeFn = Arc<dyn Fn(&Map<String, Value>) -> Value + Send + Sync>;
+
+
+#[derive(Clone)]
+enum Source {
+    Path(Vec<PathSegment>),
+    Computed(ComputeFn),
+}
+
+
+#[derive(Clone)]
+struct Field {
+    source: Source,
+    target: Vec<PathSegment>,
+    default: Option<Value>,
+}
+
+
+#[derive(Clone, Default)]
+pub struct Projection {
+    copy_all: bool,
+    excludes: Vec<Vec<PathSegment>>,
+    fields: Vec<Field>,
+}
+
+
+impl fmt::Debug for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self.fields.iter().map(|field| match &field.source {
+            Source::Path(source) => format!("{} as {}", format_path(source), format_path(&field.target)),
+            Source::Computed(_) => format!("<computed> as {}", format_path(&field.target)),
+        }).collect();
+        let excludes: Vec<String> = self.excludes.iter().map(|e| format_path(e)).collect();
+        f.debug_struct("Projection")
+            .field("copy_all", &self.copy_all)
+            .field("excludes", &excludes)
+            .field("fields", &fields)
+            .finish()
+    }
+}
+
+
+fn parse_target(path: &str) -> Result<Vec<PathSegment>, PathError> {
+    let segments = parse_path(path.trim())?;
+    if segments.is_empty() {
+        return Err(PathError::Syntax { path: path.to_string(), position: 0 });
+    }
+    Ok(segments)
+}
+
+
+impl Projection {
+
+    // starts from an empty document, only listed fields are copied...
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // starts from a full copy, use exclude() to drop paths...
+    pub fn all() -> Self {
+        Self { copy_all: true, ..Self::default() }
+    }
+
+    // builds a projection from a list of "source" / "source as target" specs...
+    pub fn compile<'a>(specs: impl IntoIterator<Item = &'a str>) -> Result<Self, PathError> {
+        specs.into_iter().try_fold(Self::new(), |projection, spec| projection.field(spec))
+    }
+
+    pub fn field(self, spec: &str) -> Result<Self, PathError> {
+        self.add_field(spec, None)
+    }
+
+    pub fn field_or(self, spec: &str, default: Value) -> Result<Self, PathError> {
+        self.add_field(spec, Some(default))
+    }
+
+    fn add_field(mut self, spec: &str, default: Option<Value>) -> Result<Self, PathError> {
+        let (source, target) = match spec.split_once(" as ") {
+            Some((source, target)) => (parse_target(source)?, parse_target(target)?),
+            None => {
+                let source = parse_target(spec)?;
+                (source.clone(), source)
+            },
+        };
+        self.fields.push(Field { source: Source::Path(source), target, default });
+        Ok(self)
+    }
+
+    pub fn computed<F>(mut self, target: &str, compute: F) -> Result<Self, PathError>
+    where F: Fn(&Map<String, Value>) -> Value + Send + Sync + 'static {
+        self.fields.push(Field { source: Source::Computed(Arc::new(compute)), target: parse_target(target)?, default: None });
+        Ok(self)
+    }
+
+    pub fn exclude(mut self, path: &str) -> Result<Self, PathError> {
+        self.excludes.push(parse_target(path)?);
+        Ok(self)
+    }
+
+    pub fn apply(&self, element: &Map<String, Value>) -> Map<String, Value> {
+
+        let source = Value::Object(element.clone());
+        let mut out = if self.copy_all { source.clone() } else { Value::Object(Map::new()) };
+
+        for exclude in &self.excludes {
+            remove_segments(&mut out, exclude);
+        }
+
+        for field in &self.fields {
+            let value = match &field.source {
+                Source::Path(path) => get_segments(&source, path).cloned().or_else(|| field.default.clone()),
+                Source::Computed(compute) => Some(compute(element)),
+            };
+            if let Some(value) = value {
+                // targets never clash with the root object so this cannot fail on an object...
+                let _ = set_segments(&mut out, &field.target, value);
+            }
+        }
+
+        match out {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        }
+
+    }
+
+    pub fn apply_all(&self, elements: &[Map<String, Value>]) -> Vec<Map<String, Value>> {
+        elements.iter().map(|e| self.apply(e)).collect()
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Map, Value, json};
+
+    use crate::maputils::{val_tostr, val_tou64};
+
+    use super::Projection;
+
+    #[test]
+    fn projectiontests() {
+
+        let doc: Map<String, Value> = serde_json::from_value(json!({
+            "id": 7, "name": "sam", "user": {"address": {"city": "leeds", "zip": "ls1"}, "password": "x"}
+        })).unwrap();
+
+        let v1 = Projection::compile(["id as user_id", "user.address.city"]).unwrap()
+            .field_or("status", json!("active")).unwrap()
+            .field("missing").unwrap()
+            .computed("meta.label", |d| json!(format!("{}#{}", val_tostr(d, "name", ""), val_tou64(d, "id", 0)))).unwrap();
+
+        assert_eq!(Value::Object(v1.apply(&doc)), json!({
+            "user_id": 7, "user": {"address": {"city": "leeds"}}, "status": "active", "meta": {"label": "sam#7"}
+        }));
+
+        let public = Projection::all().exclude("user.password").unwrap().field("name as display").unwrap();
+        let out = public.apply(&doc);
+        assert!(out["user"].get("password").is_none());
+        assert_eq!(out["display"], json!("sam"));
+        assert_eq!(out["id"], json!(7));
+
+        assert!(Projection::new().field("a as ").is_err());
+
+    }
+
+}
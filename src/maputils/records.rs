@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde_json::{Map, Value};
+
+use super::{MapExt, map_lookup};
+use crate::serdeutils::FromLenient;
+use crate::serdeutils::to_canonical_string;
+
+
+// report style operations over row sets as handed back by the db layer. field lookups use the
+// same coercions as val_to* / MapExt, so "12" and 12 sort and sum alike, and keys may be paths.
+
+
+pub type Record = Map<String, Value>;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub key: String,
+    pub descending: bool,
+}
+
+
+impl SortKey {
+
+    pub fn asc(key: &str) -> Self {
+        Self { key: key.to_string(), descending: false }
+    }
+
+    pub fn desc(key: &str) -> Self {
+        Self { key: key.to_string(), descending: true }
+    }
+
+    // "name" sorts ascending, "-name" descending...
+    pub fn parse(spec: &str) -> Self {
+        match spec.strip_prefix('-') {
+            Some(key) => Self::desc(key),
+            None => Self::asc(spec.strip_prefix('+').unwrap_or(spec)),
+        }
+    }
+
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+
+fn is_missing(record: &Record, key: &str) -> bool {
+    matches!(map_lookup(record, key), None | Some(Value::Null))
+}
+
+
+fn field_text(record: &Record, key: &str) -> Option<String> {
+    record.try_get_path(key).ok()
+}
+
+
+// a field as it sorts, variants are in rank order so mixed columns still give a total order...
+enum SortValue {
+    Null,
+    Number(f64),
+    Bool(bool),
+    Text(String),
+}
+
+
+impl SortValue {
+
+    // numeric strings count as numbers, the same coercion val_tofloat applies...
+    fn read(record: &Record, key: &str) -> Self {
+        match map_lookup(record, key) {
+            None | Some(Value::Null) => Self::Null,
+            Some(Value::Bool(b)) => Self::Bool(*b),
+            Some(value) => match f64::from_lenient(value) {
+                Ok(n) => Self::Number(n),
+                Err(_) => Self::Text(String::from_lenient(value).unwrap_or_else(|_| value.to_string())),
+            },
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Number(_) => 1,
+            Self::Bool(_) => 2,
+            Self::Text(_) => 3,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Number(x), Self::Number(y)) => x.total_cmp(y),
+            (Self::Bool(x), Self::Bool(y)) => x.cmp(y),
+            (Self::Text(x), Self::Text(y)) => x.cmp(y),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+}
+
+
+// null < numbers (including numeric strings) < booleans < text...
+fn compare_field(a: &Record, b: &Record, key: &str) -> Ordering {
+    SortValue::read(a, key).compare(&SortValue::read(b, key))
+}
+
+
+pub fn sort_records(records: &mut [Record], keys: &[SortKey]) {
+    records.sort_by(|a, b| {
+        keys.iter().fold(Ordering::Equal, |ordering, sort| {
+            ordering.then_with(|| {
+                // missing values stay last whichever way the key sorts...
+                match (is_missing(a, &sort.key), is_missing(b, &sort.key)) {
+                    (false, true) => Ordering::Less,
+                    (true, false) => Ordering::Greater,
+                    (true, true) => Ordering::Equal,
+                    _ if sort.descending => compare_field(a, b, &sort.key).reverse(),
+                    _ => compare_field(a, b, &sort.key),
+                }
+            })
+        })
+    });
+}
+
+
+pub fn group_by(records: &[Record], key: &str) -> BTreeMap<String, Vec<Record>> {
+    let mut out: BTreeMap<String, Vec<Record>> = BTreeMap::new();
+    for record in records {
+        out.entry(field_text(record, key).unwrap_or_default()).or_default().push(record.clone());
+    }
+    out
+}
+
+
+// keeps the first record for each combination of keys, no keys compares whole records...
+pub fn distinct(records: &[Record], keys: &[&str]) -> Vec<Record> {
+    let mut seen = HashSet::new();
+    records
+        .iter()
+        .filter(|record| {
+            let identity = if keys.is_empty() {
+                to_canonical_string(&Value::Object((*record).clone()))
+            } else {
+                keys.iter().map(|k| to_canonical_string(&Value::from(field_text(record, k)))).collect::<Vec<_>>().join("\u{1f}")
+            };
+            seen.insert(identity)
+        })
+        .cloned()
+        .collect()
+}
+
+
+// later records win when keys repeat, records without the key are skipped...
+pub fn index_by(records: &[Record], key: &str) -> HashMap<String, Record> {
+    records
+        .iter()
+        .filter_map(|record| field_text(record, key).map(|k| (k, record.clone())))
+        .collect()
+}
+
+
+// joins on left_key == right_key, right fields are added to the left record without overwriting
+// existing fields. a right side with repeated keys produces one row per match...
+pub fn join(left: &[Record], right: &[Record], left_key: &str, right_key: &str, kind: JoinKind) -> Vec<Record> {
+
+    let mut lookup: HashMap<String, Vec<&Record>> = HashMap::new();
+    for record in right {
+        if let Some(key) = field_text(record, right_key) {
+            lookup.entry(key).or_default().push(record);
+        }
+    }
+
+    let mut out = Vec::new();
+
+    for record in left {
+        let matches = field_text(record, left_key).and_then(|k| lookup.get(&k));
+        match matches {
+            Some(found) => {
+                for other in found {
+                    let mut row = record.clone();
+                    for (key, value) in other.iter() {
+                        row.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                    out.push(row);
+                }
+            },
+            None if kind == JoinKind::Left => out.push(record.clone()),
+            None => {},
+        }
+    }
+
+    out
+
+}
+
+
+// Count counts records holding a non null value, the rest ignore values that are not numeric...
+pub fn aggregate(records: &[Record], key: &str, function: Aggregate) -> Value {
+
+    let numbers = || records.iter().filter_map(|r| r.try_get_f64(key).ok());
+
+    let result = match function {
+        Aggregate::Count => return Value::from(records.iter().filter(|r| !is_missing(r, key)).count()),
+        Aggregate::Sum => Some(numbers().sum()),
+        Aggregate::Avg => {
+            let (total, count) = numbers().fold((0.0, 0usize), |(total, count), n| (total + n, count + 1));
+            (count > 0).then(|| total / count as f64)
+        },
+        Aggregate::Min => numbers().reduce(f64::min),
+        Aggregate::Max => numbers().reduce(f64::max),
+    };
+
+    let Some(result) = result else {
+        return Value::Null;
+    };
+
+    // keep whole numbers as integers so totals serialise as 3 rather than 3.0...
+    if result.fract() == 0.0 && result.abs() < i64::MAX as f64 {
+        Value::from(result as i64)
+    } else {
+        Value::from(result)
+    }
+
+}
+
+
+// one row per group holding the group key and each (output, function, field) aggregate...
+pub fn group_aggregate(records: &[Record], group_key: &str, aggregates: &[(&str, Aggregate, &str)]) -> Vec<Record> {
+    group_by(records, group_key)
+        .into_iter()
+        .map(|(group, rows)| {
+            let mut out = Record::new();
+            out.insert(group_key.to_string(), Value::from(group));
+            for (name, function, field) in aggregates {
+                out.insert(name.to_string(), aggregate(&rows, field, *function));
+            }
+            out
+        })
+        .collect()
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Value, json};
+
+    use super::{Aggregate, JoinKind, Record, SortKey, aggregate, distinct, group_aggregate, group_by, index_by, join, sort_records};
+
+    fn records(value: Value) -> Vec<Record> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn recordtests() {
+
+        let mut rows = records(json!([
+            {"id": 1, "region": "north", "amount": "10", "name": "b"},
+            {"id": 2, "region": "south", "amount": 5, "name": "a"},
+            {"id": 3, "region": "north", "amount": 2.5, "name": "c"},
+            {"id": 4, "region": "south", "name": "d"}
+        ]));
+
+        sort_records(&mut rows, &[SortKey::asc("region"), SortKey::parse("-amount")]);
+        let ids: Vec<_> = rows.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids, vec![json!(1), json!(3), json!(2), json!(4)]);
+
+        let mut mixed = records(json!([{"v": "2"}, {"v": "10"}, {"v": "1x"}, {"v": true}, {"v": 1.5}, {"v": "b"}]));
+        sort_records(&mut mixed, &[SortKey::asc("v")]);
+        let order: Vec<_> = mixed.iter().map(|r| r["v"].clone()).collect();
+        assert_eq!(order, vec![json!(1.5), json!("2"), json!("10"), json!(true), json!("1x"), json!("b")]);
+
+        assert_eq!(group_by(&rows, "region")["north"].len(), 2);
+        assert_eq!(distinct(&rows, &["region"]).len(), 2);
+        assert_eq!(index_by(&rows, "id")["3"]["name"], json!("c"));
+
+        assert_eq!(aggregate(&rows, "amount", Aggregate::Sum), json!(17.5));
+        assert_eq!(aggregate(&rows, "amount", Aggregate::Count), json!(3));
+        assert_eq!(aggregate(&rows, "amount", Aggregate::Max), json!(10));
+        assert_eq!(aggregate(&rows, "missing", Aggregate::Avg), Value::Null);
+        assert_eq!(aggregate(&rows, "missing", Aggregate::Sum), json!(0));
+        assert_eq!(aggregate(&rows, "missing", Aggregate::Count), json!(0));
+
+        let totals = group_aggregate(&rows, "region", &[("total", Aggregate::Sum, "amount"), ("rows", Aggregate::Count, "id")]);
+        assert_eq!(Value::from(totals), json!([
+            {"region": "north", "total": 12.5, "rows": 2},
+            {"region": "south", "total": 5, "rows": 2}
+        ]));
+
+        let regions = records(json!([{"code": "north", "manager": "sam"}]));
+        let joined = join(&rows, &regions, "region", "code", JoinKind::Inner);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined[0]["manager"], json!("sam"));
+        assert_eq!(join(&rows, &regions, "region", "code", JoinKind::Left).len(), 4);
+
+    }
+
+}
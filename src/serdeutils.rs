@@ -1,204 +1,279 @@
 
-use serde_json::Value;
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::genericutils::{FieldError, FieldErrorKind};
+
+pub mod jsonpath;
+pub mod template;
+pub mod lenient;
+pub mod patch;
+pub mod diff;
+pub mod flatten;
+pub mod canonical;
+pub mod schema;
+pub mod redact;
+pub mod ndjson;
+
+pub use jsonpath::{JsonPath, QueryError, QueryMatch, query};
+pub use template::{Template, TemplateError, render_template};
+pub use canonical::{hash_value, to_canonical_string};
+pub use diff::{DiffOptions, JsonChange, format_diff, json_diff, json_diff_with};
+pub use flatten::{FlattenOptions, IndexStyle, flatten, flatten_with, unflatten, unflatten_with};
+pub use schema::{Schema, SchemaError, Violation, ViolationKind};
+pub use redact::{RedactAction, Redactor, redact, redacted};
+pub use ndjson::{NdjsonError, NdjsonReader, NdjsonWriter, to_ndjson};
+pub use patch::{PatchError, PatchOperation, apply_patch, apply_patch_value, merge_patch, merge_patch_diff};
 
 
 pub struct ParseValue<'a, T>(pub &'a Value, pub &'a str, pub T);
 
 
-impl <'a> From<ParseValue<'a, i8>> for i8 {
-
-    fn from(src: ParseValue<i8>) -> i8 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_u64() {
-                    number as i8
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<i8>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
-                }
-            }
-        }
+// lenient conversion from a json value, numbers may arrive as strings and booleans as 1/0,
+// integer conversions are range checked rather than wrapped...
+pub trait FromLenient: Sized {
+
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind>;
+
+    // value used when the key is missing or null, None means that is an error...
+    fn from_missing() -> Option<Self> {
+        None
     }
 
 }
 
 
-impl <'a> From<ParseValue<'a, u8>> for u8 {
-
-    fn from(src: ParseValue<u8>) -> u8 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_u64() {
-                    number as u8
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<u8>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
-                }
-            }
-        }
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
     }
+}
+
 
+// unsigned values are kept apart so the full u128 range survives the conversion...
+enum LenientInteger {
+    Signed(i128),
+    Unsigned(u128),
 }
 
 
-impl <'a> From<ParseValue<'a, i32>> for i32 {
+fn lenient_integer(value: &Value, expected: &'static str) -> Result<LenientInteger, FieldErrorKind> {
 
-    fn from(src: ParseValue<i32>) -> i32 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_i64() {
-                    number as i32
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<i32>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
+    let invalid = || FieldErrorKind::Invalid { value: describe_value(value), expected };
+
+    match value {
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                Ok(LenientInteger::Unsigned(u as u128))
+            } else if let Some(i) = n.as_i64() {
+                Ok(LenientInteger::Signed(i as i128))
+            } else {
+                match n.as_f64() {
+                    Some(f) if f.fract() != 0.0 || !f.is_finite() => Err(invalid()),
+                    Some(f) if (0.0..3.4e38).contains(&f) => Ok(LenientInteger::Unsigned(f as u128)),
+                    Some(f) if (-1.7e38..0.0).contains(&f) => Ok(LenientInteger::Signed(f as i128)),
+                    _ => Err(invalid()),
                 }
             }
-        }
+        },
+        Value::String(s) => {
+            let text = s.trim();
+            match text.starts_with('-') {
+                true => text.parse::<i128>().map(LenientInteger::Signed).map_err(|_| invalid()),
+                false => text.parse::<u128>().map(LenientInteger::Unsigned).map_err(|_| invalid()),
+            }
+        },
+        _ => Err(invalid()),
     }
 
 }
 
 
-impl <'a> From<ParseValue<'a, u32>> for u32 {
-
-    fn from(src: ParseValue<u32>) -> u32 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_u64() {
-                    number as u32
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<u32>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
+macro_rules! lenient_integers {
+    ($($t:ty),*) => {
+        $(
+            impl FromLenient for $t {
+                fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+                    let (converted, number) = match lenient_integer(value, stringify!($t))? {
+                        LenientInteger::Signed(i) => (<$t>::try_from(i).ok(), i.to_string()),
+                        LenientInteger::Unsigned(u) => (<$t>::try_from(u).ok(), u.to_string()),
+                    };
+                    converted.ok_or(FieldErrorKind::OutOfRange { value: number, expected: stringify!($t) })
                 }
             }
-        }
+        )*
+    };
+}
+
+
+lenient_integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+
+impl FromLenient for f64 {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        let parsed = match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        };
+        // "NaN" and "inf" parse as f64 but are not json numbers...
+        parsed.filter(|f| f.is_finite()).ok_or_else(|| FieldErrorKind::Invalid { value: describe_value(value), expected: "f64" })
     }
+}
+
 
+impl FromLenient for f32 {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        let number = f64::from_lenient(value).map_err(|_| FieldErrorKind::Invalid { value: describe_value(value), expected: "f32" })?;
+        if number.is_finite() && number.abs() > f32::MAX as f64 {
+            return Err(FieldErrorKind::OutOfRange { value: number.to_string(), expected: "f32" });
+        }
+        Ok(number as f32)
+    }
 }
 
 
-impl <'a> From<ParseValue<'a, u64>> for u64 {
+impl FromLenient for bool {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        let parsed = match value {
+            Value::Bool(b) => Some(*b),
+            Value::Number(n) => match n.as_f64() {
+                Some(1.0) => Some(true),
+                Some(0.0) => Some(false),
+                _ => None,
+            },
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Some(true),
+                "0" | "false" | "no" | "off" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        };
+        parsed.ok_or_else(|| FieldErrorKind::Invalid { value: describe_value(value), expected: "bool" })
+    }
+}
 
-    fn from(src: ParseValue<u64>) -> u64 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_u64() {
-                    number
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<u64>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
-                }
-            }
+
+impl FromLenient for String {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(_) | Value::Bool(_) => Ok(value.to_string()),
+            _ => Err(FieldErrorKind::Invalid { value: describe_value(value), expected: "string" }),
         }
     }
-
 }
 
 
-impl <'a> From<ParseValue<'a, i64>> for i64 {
+impl <T: FromLenient> FromLenient for Option<T> {
 
-    fn from(src: ParseValue<i64>) -> i64 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_i64() {
-                    number
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<i64>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
-                }
-            }
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        if value.is_null() {
+            Ok(None)
+        } else {
+            T::from_lenient(value).map(Some)
         }
     }
 
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+
+}
+
+
+// arrays convert element by element, a single value becomes a one item list...
+impl <T: FromLenient> FromLenient for Vec<T> {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        match value {
+            Value::Array(values) => values.iter().map(T::from_lenient).collect(),
+            _ => Ok(vec![T::from_lenient(value)?]),
+        }
+    }
 }
 
 
-impl <'a> From<ParseValue<'a, f64>> for f64 {
+impl FromLenient for NaiveDate {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        if let Some(s) = value.as_str()
+            && let Ok(date) = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+                return Ok(date);
+            }
+        DateTime::<Utc>::from_lenient(value)
+            .map(|d| d.date_naive())
+            .map_err(|_| FieldErrorKind::Invalid { value: describe_value(value), expected: "date" })
+    }
+}
+
 
-    fn from(src: ParseValue<f64>) -> f64 {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(number) = e.as_f64() {
-                    number
-                } else {            
-                    match e.as_str() {
-                        Some(num) => match num.parse::<f64>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2,
-                    }                        
+impl FromLenient for NaiveDateTime {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        if let Some(s) = value.as_str() {
+            for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+                if let Ok(datetime) = NaiveDateTime::parse_from_str(s.trim(), format) {
+                    return Ok(datetime);
                 }
             }
         }
+        DateTime::<Utc>::from_lenient(value)
+            .map(|d| d.naive_utc())
+            .map_err(|_| FieldErrorKind::Invalid { value: describe_value(value), expected: "datetime" })
     }
+}
+
 
+// rfc3339 strings or epoch seconds (as a number or numeric string)...
+impl FromLenient for DateTime<Utc> {
+    fn from_lenient(value: &Value) -> Result<Self, FieldErrorKind> {
+        let invalid = || FieldErrorKind::Invalid { value: describe_value(value), expected: "datetime" };
+        if let Some(s) = value.as_str()
+            && let Ok(datetime) = DateTime::parse_from_rfc3339(s.trim()) {
+                return Ok(datetime.with_timezone(&Utc));
+            }
+        let epoch = i64::from_lenient(value).map_err(|_| invalid())?;
+        DateTime::from_timestamp(epoch, 0).ok_or_else(invalid)
+    }
 }
 
 
-impl <'a> From<ParseValue<'a, bool>> for bool {
+// exact top level keys win, otherwise the key is read as a path ("meta.limits[0].max")...
+pub fn lookup_value<'a>(element: &'a Value, key: &str) -> Option<&'a Value> {
+    element.get(key).or_else(|| get_path(element, key))
+}
 
-    fn from(src: ParseValue<bool>) -> bool {
-        match src.0.get(src.1) {
-            None => src.2,
-            Some(e) => {
-                if let Some(boolean) = e.as_bool() {
-                    boolean
-                } else if e.is_number() {                
-                    e.as_i64() == Some(1)
-                } else {
-                    match e.as_str() {
-                        Some(num) => match num.parse::<bool>() {
-                            Ok(parsed) => parsed,
-                            Err(_) => src.2,
-                        },
-                        _ => src.2
-                    }      
-                }
-            }
-        }
+
+pub fn try_parse<T: FromLenient>(element: &Value, key: &str) -> Result<T, FieldError> {
+    match lookup_value(element, key) {
+        None | Some(Value::Null) => T::from_missing().ok_or_else(|| FieldError::missing(key)),
+        Some(value) => T::from_lenient(value).map_err(|kind| FieldError { key: key.to_string(), kind }),
     }
+}
 
+
+pub fn parse_or<T: FromLenient>(element: &Value, key: &str, default: T) -> T {
+    try_parse(element, key).unwrap_or(default)
 }
 
 
+macro_rules! parse_value_from {
+    ($($t:ty),*) => {
+        $(
+            impl <'a> From<ParseValue<'a, $t>> for $t {
+                fn from(src: ParseValue<$t>) -> $t {
+                    parse_or(src.0, src.1, src.2)
+                }
+            }
+        )*
+    };
+}
+
+
+parse_value_from!(i8, u8, i32, u32, u64, i64, f64, bool);
+
+
 fn parse_index_segment(segment: &str) -> Option<(&str, usize)> {
     let open_index = segment.find('[')?;
     let close_index = segment[open_index + 1..].find(']')? + open_index + 1;
@@ -216,7 +291,7 @@ fn parse_index_segment(segment: &str) -> Option<(&str, usize)> {
 
 pub fn fetch_string(element: &serde_json::Value, key: &str, default: &str) -> Option<String> {
 
-    let strout = match element.get(key) {
+    let strout = match lookup_value(element, key) {
         Some(e) => {
             if e.is_string() {
                 e.as_str().expect("").to_string()
@@ -238,46 +313,91 @@ pub fn fetch_string(element: &serde_json::Value, key: &str, default: &str) -> Op
 }   
 
 
-pub fn parse_i32(element: &serde_json::Value, key: &'static str, default: i32) -> i32 {
+pub fn parse_i32(element: &serde_json::Value, key: &str, default: i32) -> i32 {
     i32::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_u32(element: &serde_json::Value, key: &'static str, default: u32) -> u32 {
+pub fn parse_u32(element: &serde_json::Value, key: &str, default: u32) -> u32 {
     u32::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_u64(element: &serde_json::Value, key: &'static str, default: u64) -> u64 {
+pub fn parse_u64(element: &serde_json::Value, key: &str, default: u64) -> u64 {
     u64::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_i64(element: &serde_json::Value, key: &'static str, default: i64) -> i64 {
+pub fn parse_i64(element: &serde_json::Value, key: &str, default: i64) -> i64 {
     i64::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_u8(element: &serde_json::Value, key: &'static str, default: u8) -> u8 {
+pub fn parse_u8(element: &serde_json::Value, key: &str, default: u8) -> u8 {
     u8::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_i8(element: &serde_json::Value, key: &'static str, default: i8) -> i8 {
+pub fn parse_i8(element: &serde_json::Value, key: &str, default: i8) -> i8 {
     i8::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_bool(element: &serde_json::Value, key: &'static str, default: bool) -> bool {
+pub fn parse_bool(element: &serde_json::Value, key: &str, default: bool) -> bool {
     bool::from(ParseValue(element, key, default))
 }
 
 
-pub fn parse_f64(element: &serde_json::Value, key: &'static str, default: f64) -> f64 {
+pub fn parse_f64(element: &serde_json::Value, key: &str, default: f64) -> f64 {
     f64::from(ParseValue(element, key, default))
 }
 
 
+pub fn try_parse_i32(element: &serde_json::Value, key: &str) -> Result<i32, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_u32(element: &serde_json::Value, key: &str) -> Result<u32, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_u64(element: &serde_json::Value, key: &str) -> Result<u64, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_i64(element: &serde_json::Value, key: &str) -> Result<i64, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_u8(element: &serde_json::Value, key: &str) -> Result<u8, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_i8(element: &serde_json::Value, key: &str) -> Result<i8, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_bool(element: &serde_json::Value, key: &str) -> Result<bool, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_f64(element: &serde_json::Value, key: &str) -> Result<f64, FieldError> {
+    try_parse(element, key)
+}
+
+
+pub fn try_parse_string(element: &serde_json::Value, key: &str) -> Result<String, FieldError> {
+    try_parse(element, key)
+}
+
+
 pub fn recurse_value(
     
     path: &str,
@@ -347,4 +467,369 @@ pub fn recurse_value(
 
     change
 
-}
\ No newline at end of file
+}
+
+
+
+// path access, same syntax as recurse_value ("a.b[2].c") plus quoted keys (a["x.y"]) for
+// keys that contain delimiters...
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    Syntax { path: String, position: usize },
+    Conflict { path: String },
+    IndexOutOfRange { path: String, index: usize },
+}
+
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { path, position } => write!(f, "invalid path '{path}' at position {position}"),
+            Self::Conflict { path } => write!(f, "value at '{path}' is not an object or array"),
+            Self::IndexOutOfRange { path, index } => write!(f, "index {index} is too far past the end of the array at '{path}'"),
+        }
+    }
+}
+
+
+impl std::error::Error for PathError {}
+
+
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['.', '[', ']', '"', '\\', '*', '?', '@', '$', ':'])
+}
+
+
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, PathError> {
+
+    let syntax = |position: usize| PathError::Syntax { path: path.to_string(), position };
+
+    let bytes = path.as_bytes();
+    let mut out = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+
+        match bytes[pos] {
+
+            b'[' => {
+                if bytes.get(pos + 1) == Some(&b'"') {
+                    let mut key = String::new();
+                    let mut chars = path[pos + 2..].char_indices();
+                    let mut end = None;
+                    while let Some((offset, c)) = chars.next() {
+                        match c {
+                            '\\' => match chars.next() {
+                                Some((_, escaped)) => key.push(escaped),
+                                None => return Err(syntax(pos + 2 + offset)),
+                            },
+                            '"' => {
+                                end = Some(pos + 2 + offset + 1);
+                                break;
+                            },
+                            _ => key.push(c),
+                        }
+                    }
+                    let end = end.ok_or_else(|| syntax(path.len()))?;
+                    if bytes.get(end) != Some(&b']') {
+                        return Err(syntax(end));
+                    }
+                    out.push(PathSegment::Key(key));
+                    pos = end + 1;
+                } else {
+                    let close = path[pos..].find(']').ok_or_else(|| syntax(pos))? + pos;
+                    let index = path[pos + 1..close].trim().parse::<usize>().map_err(|_| syntax(pos + 1))?;
+                    out.push(PathSegment::Index(index));
+                    pos = close + 1;
+                }
+            },
+
+            b'.' => {
+                if pos == 0 || pos + 1 >= bytes.len() || bytes[pos + 1] == b'.' || bytes[pos + 1] == b'[' {
+                    return Err(syntax(pos));
+                }
+                pos += 1;
+            },
+
+            b']' | b'"' => return Err(syntax(pos)),
+
+            _ => {
+                if pos > 0 && bytes[pos - 1] == b']' {
+                    return Err(syntax(pos));
+                }
+                let end = path[pos..].find(['.', '[', ']', '"']).map(|e| e + pos).unwrap_or(path.len());
+                out.push(PathSegment::Key(path[pos..end].to_string()));
+                pos = end;
+            },
+
+        }
+
+    }
+
+    Ok(out)
+
+}
+
+
+pub fn format_path(segments: &[PathSegment]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        push_path_segment(&mut out, segment);
+    }
+    out
+}
+
+
+pub(crate) fn push_path_segment(out: &mut String, segment: &PathSegment) {
+    match segment {
+        PathSegment::Key(key) if is_plain_key(key) => {
+            if !out.is_empty() {
+                out.push('.');
+            }
+            out.push_str(key);
+        },
+        PathSegment::Key(key) => {
+            out.push_str("[\"");
+            for c in key.chars() {
+                if c == '"' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push_str("\"]");
+        },
+        PathSegment::Index(index) => {
+            out.push_str(&format!("[{index}]"));
+        },
+    }
+}
+
+
+pub fn get_segments<'a>(value: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    segments.iter().try_fold(value, |current, segment| match segment {
+        PathSegment::Key(key) => current.as_object()?.get(key),
+        PathSegment::Index(index) => current.as_array()?.get(*index),
+    })
+}
+
+
+pub fn get_segments_mut<'a>(value: &'a mut Value, segments: &[PathSegment]) -> Option<&'a mut Value> {
+    segments.iter().try_fold(value, |current, segment| match segment {
+        PathSegment::Key(key) => current.as_object_mut()?.get_mut(key),
+        PathSegment::Index(index) => current.as_array_mut()?.get_mut(*index),
+    })
+}
+
+
+pub fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    get_segments(value, &parse_path(path).ok()?)
+}
+
+
+pub fn get_path_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    get_segments_mut(value, &parse_path(path).ok()?)
+}
+
+
+// set_segments pads arrays with at most this many nulls, indices further past the end are rejected
+// so an untrusted path such as "x[99999999999]" cannot force a huge allocation...
+pub const MAX_ARRAY_PADDING: usize = 1024;
+
+
+// sets the value at the path, creating objects / arrays on the way (arrays are padded with nulls),
+// returns the previous value whenever the key or index already existed, including a json null...
+// walks the path without touching the value so a failed set leaves it unchanged...
+fn check_segments(value: &Value, segments: &[PathSegment]) -> Result<(), PathError> {
+
+    let mut current = Some(value).filter(|v| !v.is_null());
+
+    for (position, segment) in segments.iter().enumerate() {
+
+        let conflict = || PathError::Conflict { path: format_path(&segments[..position]) };
+
+        let padding = |len: usize, index: usize| match index - len.min(index) > MAX_ARRAY_PADDING {
+            true => Err(PathError::IndexOutOfRange { path: format_path(&segments[..position]), index }),
+            false => Ok(()),
+        };
+
+        current = match (current, segment) {
+            (None, PathSegment::Key(_)) => None,
+            (None, PathSegment::Index(index)) => padding(0, *index).map(|_| None)?,
+            (Some(Value::Object(map)), PathSegment::Key(key)) => map.get(key),
+            (Some(Value::Array(values)), PathSegment::Index(index)) => padding(values.len(), *index).map(|_| values.get(*index))?,
+            _ => return Err(conflict()),
+        }.filter(|v| !v.is_null());
+
+    }
+
+    Ok(())
+
+}
+
+
+pub fn set_segments(value: &mut Value, segments: &[PathSegment], newvalue: Value) -> Result<Option<Value>, PathError> {
+
+    check_segments(value, segments)?;
+
+    let mut current = value;
+    let mut existed = true;
+
+    for (position, segment) in segments.iter().enumerate() {
+
+        if current.is_null() {
+            *current = match segment {
+                PathSegment::Key(_) => Value::Object(Map::new()),
+                PathSegment::Index(_) => Value::Array(Vec::new()),
+            };
+        }
+
+        let conflict = || PathError::Conflict { path: format_path(&segments[..position]) };
+
+        current = match segment {
+            PathSegment::Key(key) => {
+                let map = current.as_object_mut().ok_or_else(conflict)?;
+                existed = map.contains_key(key);
+                map.entry(key.clone()).or_insert(Value::Null)
+            },
+            PathSegment::Index(index) => {
+                let values = current.as_array_mut().ok_or_else(conflict)?;
+                existed = *index < values.len();
+                if *index - values.len().min(*index) > MAX_ARRAY_PADDING {
+                    return Err(PathError::IndexOutOfRange { path: format_path(&segments[..position]), index: *index });
+                }
+                if !existed {
+                    values.resize(*index + 1, Value::Null);
+                }
+                &mut values[*index]
+            },
+        };
+
+    }
+
+    let previous = std::mem::replace(current, newvalue);
+    Ok(existed.then_some(previous))
+
+}
+
+
+pub fn set_path(value: &mut Value, path: &str, newvalue: Value) -> Result<Option<Value>, PathError> {
+    set_segments(value, &parse_path(path)?, newvalue)
+}
+
+
+// removes the value at the path, array elements are removed (shifting the remainder)...
+pub fn remove_segments(value: &mut Value, segments: &[PathSegment]) -> Option<Value> {
+    let (last, parents) = segments.split_last()?;
+    match (get_segments_mut(value, parents)?, last) {
+        (Value::Object(map), PathSegment::Key(key)) => map.remove(key),
+        (Value::Array(values), PathSegment::Index(index)) if *index < values.len() => Some(values.remove(*index)),
+        _ => None,
+    }
+}
+
+
+pub fn remove_path(value: &mut Value, path: &str) -> Option<Value> {
+    remove_segments(value, &parse_path(path).ok()?)
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use chrono::NaiveDate;
+
+    use crate::genericutils::{FieldError, FieldErrorKind};
+
+    use super::{PathError, PathSegment, format_path, get_path, parse_path, parse_u8, parse_bool, remove_path, set_path, try_parse, try_parse_u8};
+
+    #[test]
+    fn lenienttests() {
+
+        let doc = json!({"small": 300, "text": "42", "neg": -1, "flag": "1", "when": "2024-02-29", "list": ["1", 2], "nothing": null});
+
+        assert_eq!(parse_u8(&doc, "small", 7), 7);
+        assert_eq!(parse_u8(&doc, "text", 7), 42);
+        assert!(parse_bool(&doc, "flag", false));
+
+        assert_eq!(
+            try_parse_u8(&doc, "small"),
+            Err(FieldError { key: "small".to_string(), kind: FieldErrorKind::OutOfRange { value: "300".to_string(), expected: "u8" } })
+        );
+        assert!(matches!(try_parse::<u32>(&doc, "neg").unwrap_err().kind, FieldErrorKind::OutOfRange { .. }));
+        assert_eq!(try_parse::<u16>(&doc, "missing"), Err(FieldError::missing("missing")));
+
+        assert_eq!(try_parse::<Option<i64>>(&doc, "nothing"), Ok(None));
+        assert_eq!(try_parse::<Option<i64>>(&doc, "missing"), Ok(None));
+        assert_eq!(try_parse::<Vec<u64>>(&doc, "list"), Ok(vec![1, 2]));
+        assert_eq!(try_parse::<NaiveDate>(&doc, "when"), Ok(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+        assert_eq!(try_parse::<String>(&doc, "small"), Ok("300".to_string()));
+
+        let wide = json!({"max": u128::MAX.to_string(), "min": i128::MIN.to_string(), "nan": "NaN", "inf": " inf ", "real": "2.5"});
+        assert_eq!(try_parse::<u128>(&wide, "max"), Ok(u128::MAX));
+        assert_eq!(try_parse::<i128>(&wide, "min"), Ok(i128::MIN));
+        assert!(matches!(try_parse::<i128>(&wide, "max").unwrap_err().kind, FieldErrorKind::OutOfRange { .. }));
+        assert!(matches!(try_parse::<f64>(&wide, "nan").unwrap_err().kind, FieldErrorKind::Invalid { expected: "f64", .. }));
+        assert!(matches!(try_parse::<f64>(&wide, "inf").unwrap_err().kind, FieldErrorKind::Invalid { expected: "f64", .. }));
+        assert!(matches!(try_parse::<f32>(&wide, "nan").unwrap_err().kind, FieldErrorKind::Invalid { .. }));
+        assert_eq!(try_parse::<f64>(&wide, "real"), Ok(2.5));
+
+        let nested = json!({"meta": {"limits": [{"max": "12"}]}, "a.b": 1});
+        let key = String::from("meta.limits[0].max");
+
+        assert_eq!(super::parse_i32(&nested, &key, 0), 12);
+        assert_eq!(super::parse_i32(&nested, "a.b", 0), 1);
+        assert_eq!(super::fetch_string(&nested, "meta.limits[0].max", ""), Some("12".to_string()));
+        assert_eq!(try_parse_u8(&nested, "meta.limits[1].max"), Err(FieldError::missing("meta.limits[1].max")));
+
+    }
+
+    #[test]
+    fn pathtests() {
+
+        let mut doc = json!({"a": {"b": [1, 2, {"c": "found"}]}, "x.y": true});
+
+        assert_eq!(get_path(&doc, "a.b[2].c"), Some(&json!("found")));
+        assert_eq!(get_path(&doc, "[\"x.y\"]"), Some(&json!(true)));
+        assert_eq!(get_path(&doc, "a.b[9]"), None);
+        assert_eq!(get_path(&doc, ""), Some(&doc.clone()));
+
+        let segments = parse_path("a.b[2][\"c.d\"]").unwrap();
+        assert_eq!(segments[2], PathSegment::Index(2));
+        assert_eq!(format_path(&segments), "a.b[2][\"c.d\"]");
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path("a[x]").is_err());
+
+        assert_eq!(set_path(&mut doc, "a.b[2].c", json!("changed")).unwrap(), Some(json!("found")));
+        set_path(&mut doc, "n.list[2].id", json!(7)).unwrap();
+        assert_eq!(doc["n"], json!({"list": [null, null, {"id": 7}]}));
+        assert!(set_path(&mut doc, "a.b[0].c", json!(1)).is_err());
+        assert_eq!(set_path(&mut doc, "n.list[0]", json!(1)).unwrap(), Some(json!(null)));
+        assert_eq!(set_path(&mut doc, "n.list[5]", json!(1)).unwrap(), None);
+        assert_eq!(set_path(&mut doc, "n.fresh", json!(null)).unwrap(), None);
+        assert_eq!(set_path(&mut doc, "n.fresh", json!(2)).unwrap(), Some(json!(null)));
+        assert!(matches!(set_path(&mut doc, "n.list[99999999999]", json!(1)), Err(PathError::IndexOutOfRange { index: 99999999999, .. })));
+        let before = doc.clone();
+        assert!(matches!(set_path(&mut doc, "fresh.items[2].deep[5000]", json!(1)), Err(PathError::IndexOutOfRange { index: 5000, .. })));
+        assert!(matches!(set_path(&mut doc, "n.list[10].x[5000]", json!(1)), Err(PathError::IndexOutOfRange { index: 5000, .. })));
+        assert!(matches!(set_path(&mut doc, "n.list[2].id.z", json!(1)), Err(PathError::Conflict { .. })));
+        assert_eq!(doc, before);
+        assert!(set_path(&mut doc, "n.list[18446744073709551614]", json!(1)).is_err());
+
+        assert_eq!(remove_path(&mut doc, "a.b[0]"), Some(json!(1)));
+        assert_eq!(doc["a"]["b"][0], json!(2));
+        assert_eq!(remove_path(&mut doc, "a.missing"), None);
+
+    }
+
+}
@@ -0,0 +1,149 @@
+use serde_json::{Number, Value};
+
+use crate::genericutils::hash;
+
+
+// canonical json along the lines of RFC 8785 (JCS): object keys sorted by utf-16 code units,
+// no whitespace, integers written exactly and other numbers the way ECMAScript prints doubles (1.0 -> 1, 1e21, 1e-7)
+// so semantically equal documents produce identical text and hashes.
+
+
+pub fn to_canonical_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+
+// md5 of the canonical form, stable across key order and number formatting...
+pub fn hash_value(value: &Value) -> String {
+    hash(&to_canonical_string(value))
+}
+
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&canonical_number(n)),
+        Value::String(s) => write_string(s, out),
+        Value::Array(values) => {
+            out.push('[');
+            for (index, item) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        },
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        },
+    }
+}
+
+
+fn write_string(stringin: &str, out: &mut String) {
+    // serde_json escapes exactly the set JCS requires (quote, backslash, control characters)...
+    out.push_str(&serde_json::to_string(stringin).unwrap_or_default());
+}
+
+
+// integers are written verbatim, going through f64 would merge distinct values above 2^53...
+fn canonical_number(number: &Number) -> String {
+
+    if let Some(u) = number.as_u64() {
+        return u.to_string();
+    }
+
+    if let Some(i) = number.as_i64() {
+        return i.to_string();
+    }
+
+    format_double(number.as_f64().unwrap_or(0.0))
+
+}
+
+
+// ECMAScript Number::toString for finite doubles...
+pub fn format_double(number: f64) -> String {
+
+    if number == 0.0 || !number.is_finite() {
+        return "0".to_string();
+    }
+
+    let sign = if number < 0.0 { "-" } else { "" };
+
+    // rust's {:e} gives the shortest round trip digits, eg "1.2345e-7"...
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let body = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat((-n) as usize))
+    } else {
+        let e = n - 1;
+        let e_sign = if e < 0 { "-" } else { "+" };
+        if k == 1 {
+            format!("{digits}e{e_sign}{}", e.abs())
+        } else {
+            format!("{}.{}e{e_sign}{}", &digits[..1], &digits[1..], e.abs())
+        }
+    };
+
+    format!("{sign}{body}")
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{format_double, hash_value, to_canonical_string};
+
+    #[test]
+    fn canonicaltests() {
+
+        let a = json!({"b": [1.0, "x\n"], "a": {"z": null, "y": true}, "c": 1e21});
+        let b: serde_json::Value = serde_json::from_str(r#"{ "c": 1000000000000000000000, "a": { "y": true, "z": null }, "b": [ 1, "x\n" ] }"#).unwrap();
+
+        assert_eq!(to_canonical_string(&a), r#"{"a":{"y":true,"z":null},"b":[1,"x\n"],"c":1e+21}"#);
+        assert_eq!(hash_value(&a), hash_value(&b));
+        assert_ne!(hash_value(&a), hash_value(&json!({"a": 1})));
+
+        assert_eq!(format_double(0.000001), "0.000001");
+        assert_eq!(format_double(0.0000001), "1e-7");
+        assert_eq!(format_double(-123.456), "-123.456");
+        assert_eq!(format_double(4.5e300), "4.5e+300");
+
+        assert_eq!(to_canonical_string(&json!(9007199254740993u64)), "9007199254740993");
+        assert_eq!(to_canonical_string(&json!(-9007199254740993i64)), "-9007199254740993");
+        assert_ne!(hash_value(&json!(9007199254740993u64)), hash_value(&json!(9007199254740992u64)));
+        assert_eq!(to_canonical_string(&json!(u64::MAX)), "18446744073709551615");
+
+    }
+
+}
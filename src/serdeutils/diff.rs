@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{PathSegment, format_path};
+
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum JsonChange {
+    Added { path: String, value: Value },
+    Removed { path: String, value: Value },
+    Changed { path: String, old: Value, new: Value },
+}
+
+
+impl JsonChange {
+
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => path,
+        }
+    }
+
+}
+
+
+impl fmt::Display for JsonChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |path: &str| if path.is_empty() { "<root>".to_string() } else { path.to_string() };
+        match self {
+            Self::Added { path, value } => write!(f, "+ {}: {value}", label(path)),
+            Self::Removed { path, value } => write!(f, "- {}: {value}", label(path)),
+            Self::Changed { path, old, new } => write!(f, "~ {}: {old} -> {new}", label(path)),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub ignore_keys: HashSet<String>,
+    pub ignore_array_order: bool,
+}
+
+
+impl DiffOptions {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ignore_key(mut self, key: &str) -> Self {
+        self.ignore_keys.insert(key.to_string());
+        self
+    }
+
+    pub fn ignore_array_order(mut self, ignore: bool) -> Self {
+        self.ignore_array_order = ignore;
+        self
+    }
+
+}
+
+
+pub fn json_diff(left: &Value, right: &Value) -> Vec<JsonChange> {
+    json_diff_with(left, right, &DiffOptions::default())
+}
+
+
+pub fn json_diff_with(left: &Value, right: &Value, options: &DiffOptions) -> Vec<JsonChange> {
+    let mut out = Vec::new();
+    let mut path = Vec::new();
+    diff_values(left, right, options, &mut path, &mut out);
+    out
+}
+
+
+// one change per line, handy for audit logs and assertion messages...
+pub fn format_diff(changes: &[JsonChange]) -> String {
+    changes.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+
+fn scalar_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l == r || (l.as_f64().is_some() && l.as_f64() == r.as_f64()),
+        _ => left == right,
+    }
+}
+
+
+fn values_equal(left: &Value, right: &Value, options: &DiffOptions) -> bool {
+    let mut out = Vec::new();
+    diff_values(left, right, options, &mut Vec::new(), &mut out);
+    out.is_empty()
+}
+
+
+fn child_path(path: &[PathSegment], segment: PathSegment) -> String {
+    let mut full = path.to_vec();
+    full.push(segment);
+    format_path(&full)
+}
+
+
+fn diff_values(left: &Value, right: &Value, options: &DiffOptions, path: &mut Vec<PathSegment>, out: &mut Vec<JsonChange>) {
+
+    match (left, right) {
+
+        (Value::Object(l), Value::Object(r)) => {
+
+            for (key, value) in l {
+                if options.ignore_keys.contains(key) {
+                    continue;
+                }
+                match r.get(key) {
+                    Some(other) => {
+                        path.push(PathSegment::Key(key.clone()));
+                        diff_values(value, other, options, path, out);
+                        path.pop();
+                    },
+                    None => out.push(JsonChange::Removed { path: child_path(path, PathSegment::Key(key.clone())), value: value.clone() }),
+                }
+            }
+
+            for (key, value) in r {
+                if !options.ignore_keys.contains(key) && !l.contains_key(key) {
+                    out.push(JsonChange::Added { path: child_path(path, PathSegment::Key(key.clone())), value: value.clone() });
+                }
+            }
+
+        },
+
+        (Value::Array(l), Value::Array(r)) if options.ignore_array_order => {
+
+            let mut matched = vec![false; r.len()];
+
+            for (index, value) in l.iter().enumerate() {
+                let found = r.iter().enumerate().position(|(i, other)| !matched[i] && values_equal(value, other, options));
+                match found {
+                    Some(i) => matched[i] = true,
+                    None => out.push(JsonChange::Removed { path: child_path(path, PathSegment::Index(index)), value: value.clone() }),
+                }
+            }
+
+            for (index, value) in r.iter().enumerate() {
+                if !matched[index] {
+                    out.push(JsonChange::Added { path: child_path(path, PathSegment::Index(index)), value: value.clone() });
+                }
+            }
+
+        },
+
+        (Value::Array(l), Value::Array(r)) => {
+
+            for (index, value) in l.iter().enumerate() {
+                match r.get(index) {
+                    Some(other) => {
+                        path.push(PathSegment::Index(index));
+                        diff_values(value, other, options, path, out);
+                        path.pop();
+                    },
+                    None => out.push(JsonChange::Removed { path: child_path(path, PathSegment::Index(index)), value: value.clone() }),
+                }
+            }
+
+            for (index, value) in r.iter().enumerate().skip(l.len()) {
+                out.push(JsonChange::Added { path: child_path(path, PathSegment::Index(index)), value: value.clone() });
+            }
+
+        },
+
+        _ => {
+            if !scalar_equal(left, right) {
+                out.push(JsonChange::Changed { path: format_path(path), old: left.clone(), new: right.clone() });
+            }
+        },
+
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{DiffOptions, JsonChange, format_diff, json_diff, json_diff_with};
+
+    #[test]
+    fn difftests() {
+
+        let before = json!({"name": "sam", "tags": ["a", "b"], "address": {"city": "london", "zip": "n1"}, "updated": 1, "score": 1});
+        let after = json!({"name": "sam", "tags": ["b", "a", "c"], "address": {"city": "leeds"}, "updated": 2, "score": 1.0, "new": true});
+
+        let changes = json_diff(&before, &after);
+
+        assert!(changes.contains(&JsonChange::Changed { path: "address.city".to_string(), old: json!("london"), new: json!("leeds") }));
+        assert!(changes.contains(&JsonChange::Removed { path: "address.zip".to_string(), value: json!("n1") }));
+        assert!(changes.contains(&JsonChange::Added { path: "tags[2]".to_string(), value: json!("c") }));
+        assert!(changes.contains(&JsonChange::Added { path: "new".to_string(), value: json!(true) }));
+        assert!(!changes.iter().any(|c| c.path() == "score"));
+
+        let options = DiffOptions::new().ignore_key("updated").ignore_array_order(true);
+        let paths: Vec<_> = json_diff_with(&before, &after, &options).iter().map(|c| c.path().to_string()).collect();
+        assert_eq!(paths, vec!["address.city", "address.zip", "tags[2]", "new"]);
+
+        assert_eq!(format_diff(&json_diff(&json!(1), &json!(2))), "~ <root>: 1 -> 2");
+
+    }
+
+}
@@ -0,0 +1,209 @@
+use serde_json::{Map, Value};
+
+use super::{PathError, PathSegment, parse_path, push_path_segment, set_segments};
+
+
+// flattening of nested documents to single level maps and back again. the default options
+// produce recurse_value / get_path compatible keys ("a.b[0].c"), a "/" separator with
+// separator indexes produces Btree style keys ("a/b/0/c").
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexStyle {
+    Brackets,
+    Separator,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenOptions {
+    pub separator: String,
+    pub index_style: IndexStyle,
+}
+
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self { separator: ".".to_string(), index_style: IndexStyle::Brackets }
+    }
+}
+
+
+impl FlattenOptions {
+
+    pub fn new(separator: &str, index_style: IndexStyle) -> Self {
+        Self { separator: separator.to_string(), index_style }
+    }
+
+    fn is_path_syntax(&self) -> bool {
+        self.separator == "." && self.index_style == IndexStyle::Brackets
+    }
+
+    fn join(&self, prefix: &str, segment: &PathSegment) -> String {
+
+        if self.is_path_syntax() {
+            let mut out = prefix.to_string();
+            push_path_segment(&mut out, segment);
+            return out;
+        }
+
+        let token = match segment {
+            PathSegment::Index(index) if self.index_style == IndexStyle::Brackets => {
+                return format!("{prefix}[{index}]");
+            },
+            PathSegment::Index(index) => index.to_string(),
+            PathSegment::Key(key) => key.clone(),
+        };
+
+        if prefix.is_empty() {
+            token
+        } else {
+            format!("{prefix}{}{token}", self.separator)
+        }
+
+    }
+
+    fn split(&self, key: &str) -> Result<Vec<PathSegment>, PathError> {
+
+        if self.is_path_syntax() {
+            return parse_path(key);
+        }
+
+        let mut out = Vec::new();
+
+        for token in key.split(self.separator.as_str()) {
+            match self.index_style {
+                IndexStyle::Separator => {
+                    if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit())
+                        && let Ok(index) = token.parse::<usize>() {
+                            out.push(PathSegment::Index(index));
+                            continue;
+                        }
+                    out.push(PathSegment::Key(token.to_string()));
+                },
+                IndexStyle::Brackets => {
+                    let name_end = token.find('[').unwrap_or(token.len());
+                    if name_end > 0 {
+                        out.push(PathSegment::Key(token[..name_end].to_string()));
+                    }
+                    let mut rest = &token[name_end..];
+                    while let Some(inner) = rest.strip_prefix('[') {
+                        let close = inner.find(']').ok_or_else(|| PathError::Syntax { path: key.to_string(), position: 0 })?;
+                        let index = inner[..close].parse::<usize>().map_err(|_| PathError::Syntax { path: key.to_string(), position: 0 })?;
+                        out.push(PathSegment::Index(index));
+                        rest = &inner[close + 1..];
+                    }
+                    if !rest.is_empty() {
+                        return Err(PathError::Syntax { path: key.to_string(), position: 0 });
+                    }
+                },
+            }
+        }
+
+        Ok(out)
+
+    }
+
+}
+
+
+pub fn flatten(value: &Value) -> Map<String, Value> {
+    flatten_with(value, &FlattenOptions::default())
+}
+
+
+pub fn flatten_with(value: &Value, options: &FlattenOptions) -> Map<String, Value> {
+    let mut out = Map::new();
+    flatten_into(value, String::new(), options, &mut out);
+    out
+}
+
+
+// empty objects and arrays are kept as leaves so unflatten restores them...
+fn flatten_into(value: &Value, prefix: String, options: &FlattenOptions, out: &mut Map<String, Value>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                flatten_into(child, options.join(&prefix, &PathSegment::Key(key.clone())), options, out);
+            }
+        },
+        Value::Array(values) if !values.is_empty() => {
+            for (index, child) in values.iter().enumerate() {
+                flatten_into(child, options.join(&prefix, &PathSegment::Index(index)), options, out);
+            }
+        },
+        _ => {
+            out.insert(prefix, value.clone());
+        },
+    }
+}
+
+
+pub fn unflatten(map: &Map<String, Value>) -> Result<Value, PathError> {
+    unflatten_with(map, &FlattenOptions::default())
+}
+
+
+// keys are applied in path order rather than string order so "a[10]" lands after "a[2]" and arrays
+// grow one element at a time...
+pub fn unflatten_with(map: &Map<String, Value>, options: &FlattenOptions) -> Result<Value, PathError> {
+    let mut entries = map
+        .iter()
+        .map(|(key, value)| Ok((if key.is_empty() { Vec::new() } else { options.split(key)? }, value)))
+        .collect::<Result<Vec<_>, PathError>>()?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut out = Value::Null;
+    for (segments, value) in entries {
+        set_segments(&mut out, &segments, value.clone())?;
+    }
+    if out.is_null() && map.is_empty() {
+        out = Value::Object(Map::new());
+    }
+    Ok(out)
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::{Value, json};
+
+    use super::{FlattenOptions, IndexStyle, PathError, flatten, flatten_with, unflatten, unflatten_with};
+
+    #[test]
+    fn flattentests() {
+
+        let doc = json!({"a": {"b": [{"c": 1}, 2], "x.y": "dot"}, "empty": {}, "list": []});
+
+        let flat = flatten(&doc);
+        assert_eq!(flat.get("a.b[0].c"), Some(&json!(1)));
+        assert_eq!(flat.get("a.b[1]"), Some(&json!(2)));
+        assert_eq!(flat.get("a[\"x.y\"]"), Some(&json!("dot")));
+        assert_eq!(flat.get("empty"), Some(&json!({})));
+        assert_eq!(unflatten(&flat).unwrap(), doc);
+
+        let btree = FlattenOptions::new("/", IndexStyle::Separator);
+        let flat = flatten_with(&json!({"a": {"b": [true]}}), &btree);
+        assert_eq!(flat.keys().collect::<Vec<_>>(), vec!["a/b/0"]);
+        assert_eq!(unflatten_with(&flat, &btree).unwrap(), json!({"a": {"b": [true]}}));
+
+        let form: serde_json::Map<String, Value> = serde_json::from_value(json!({
+            "user.name": "sam", "user.emails[1]": "b@x", "user.emails[0]": "a@x"
+        })).unwrap();
+        assert_eq!(unflatten(&form).unwrap(), json!({"user": {"name": "sam", "emails": ["a@x", "b@x"]}}));
+
+        let underscore = FlattenOptions::new("__", IndexStyle::Brackets);
+        let flat = flatten_with(&json!({"a": {"b": [1]}}), &underscore);
+        assert_eq!(flat.keys().collect::<Vec<_>>(), vec!["a__b[0]"]);
+        assert_eq!(unflatten_with(&flat, &underscore).unwrap(), json!({"a": {"b": [1]}}));
+
+        let long = json!({"list": (0..12).collect::<Vec<_>>()});
+        assert_eq!(unflatten(&flatten(&long)).unwrap(), long);
+
+        let huge: serde_json::Map<String, Value> = serde_json::from_value(json!({"x[18446744073709551614]": 1, "y[99999999999]": 2})).unwrap();
+        assert!(matches!(unflatten(&huge), Err(PathError::IndexOutOfRange { .. })));
+
+    }
+
+}
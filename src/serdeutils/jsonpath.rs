@@ -0,0 +1,688 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde_json::Value;
+
+use super::{PathSegment, format_path, get_segments};
+
+
+// JSONPath style queries over a Value...
+//
+//   $.items[*].id          wildcard
+//   ..price                recursive descent
+//   items[1:3]             slices (python semantics, negative indices allowed)
+//   items[-1]              index from the end
+//   items[?(@.qty > 2)]    filters with == != < <= > >=, && || ! and grouping
+//   items[?(@.sku)]        existence filter
+//
+// the leading "$" is optional so plain recurse_value paths ("a.b[2].c") are valid queries.
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub expression: String,
+    pub position: usize,
+    pub message: &'static str,
+}
+
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in '{}' at position {}", self.message, self.expression, self.position)
+    }
+}
+
+
+impl std::error::Error for QueryError {}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryMatch<'a> {
+    pub segments: Vec<PathSegment>,
+    pub value: &'a Value,
+}
+
+
+impl QueryMatch<'_> {
+
+    pub fn path(&self) -> String {
+        format_path(&self.segments)
+    }
+
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Current(Vec<PathSegment>),
+    Root(Vec<PathSegment>),
+    Literal(Value),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Exists(Operand),
+    Compare(Operand, CompareOp, Operand),
+    // && / || chains are kept flat so long chains don't build deep trees...
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Child(String),
+    Index(i64),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Filter),
+    Descendant(Box<Selector>),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    expression: String,
+    selectors: Vec<Selector>,
+}
+
+
+impl JsonPath {
+
+    pub fn compile(expression: &str) -> Result<Self, QueryError> {
+        let mut parser = Parser { src: expression, pos: 0, depth: 0 };
+        let selectors = parser.parse_query()?;
+        Ok(Self { expression: expression.to_string(), selectors })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn query<'a>(&self, root: &'a Value) -> Vec<QueryMatch<'a>> {
+
+        let mut current = vec![QueryMatch { segments: Vec::new(), value: root }];
+
+        for selector in &self.selectors {
+            let mut next = Vec::new();
+            for node in current {
+                apply_selector(selector, root, node, &mut next);
+            }
+            current = next;
+        }
+
+        current
+
+    }
+
+    pub fn query_values<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        self.query(root).into_iter().map(|m| m.value).collect()
+    }
+
+    pub fn first<'a>(&self, root: &'a Value) -> Option<&'a Value> {
+        self.query(root).into_iter().next().map(|m| m.value)
+    }
+
+}
+
+
+pub fn query<'a>(root: &'a Value, expression: &str) -> Result<Vec<QueryMatch<'a>>, QueryError> {
+    Ok(JsonPath::compile(expression)?.query(root))
+}
+
+
+fn child<'a>(node: &QueryMatch<'a>, segment: PathSegment, value: &'a Value) -> QueryMatch<'a> {
+    let mut segments = node.segments.clone();
+    segments.push(segment);
+    QueryMatch { segments, value }
+}
+
+
+fn children<'a>(node: &QueryMatch<'a>, out: &mut Vec<QueryMatch<'a>>) {
+    match node.value {
+        Value::Object(map) => {
+            out.extend(map.iter().map(|(k, v)| child(node, PathSegment::Key(k.clone()), v)));
+        },
+        Value::Array(values) => {
+            out.extend(values.iter().enumerate().map(|(i, v)| child(node, PathSegment::Index(i), v)));
+        },
+        _ => {}
+    }
+}
+
+
+fn descendants<'a>(node: QueryMatch<'a>, out: &mut Vec<QueryMatch<'a>>) {
+    let mut direct = Vec::new();
+    children(&node, &mut direct);
+    out.push(node);
+    for next in direct {
+        descendants(next, out);
+    }
+}
+
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: i64, len: usize) -> Vec<usize> {
+
+    let len = len as i64;
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    let mut out = Vec::new();
+
+    if step > 0 {
+        let mut i = start.map(normalize).unwrap_or(0).clamp(0, len);
+        let stop = end.map(normalize).unwrap_or(len).clamp(0, len);
+        while i < stop {
+            out.push(i as usize);
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
+        }
+    } else {
+        let mut i = start.map(normalize).unwrap_or(len - 1).clamp(-1, len - 1);
+        let stop = end.map(normalize).unwrap_or(-1).clamp(-1, len - 1);
+        while i > stop {
+            out.push(i as usize);
+            match i.checked_add(step) {
+                Some(next) => i = next,
+                None => break,
+            }
+        }
+    }
+
+    out
+
+}
+
+
+fn apply_selector<'a>(selector: &Selector, root: &'a Value, node: QueryMatch<'a>, out: &mut Vec<QueryMatch<'a>>) {
+
+    match selector {
+
+        Selector::Child(name) => {
+            if let Some(value) = node.value.as_object().and_then(|m| m.get(name)) {
+                out.push(child(&node, PathSegment::Key(name.clone()), value));
+            }
+        },
+
+        Selector::Index(index) => {
+            if let Some(values) = node.value.as_array()
+                && let Some(i) = resolve_index(*index, values.len()) {
+                    out.push(child(&node, PathSegment::Index(i), &values[i]));
+                }
+        },
+
+        Selector::Wildcard => children(&node, out),
+
+        Selector::Slice(start, end, step) => {
+            if let Some(values) = node.value.as_array() {
+                for i in slice_indices(*start, *end, *step, values.len()) {
+                    out.push(child(&node, PathSegment::Index(i), &values[i]));
+                }
+            }
+        },
+
+        Selector::Filter(filter) => {
+            let mut direct = Vec::new();
+            children(&node, &mut direct);
+            out.extend(direct.into_iter().filter(|m| eval_filter(filter, root, m.value)));
+        },
+
+        Selector::Descendant(inner) => {
+            let mut all = Vec::new();
+            descendants(node, &mut all);
+            for next in all {
+                apply_selector(inner, root, next, out);
+            }
+        },
+
+    }
+
+}
+
+
+fn resolve_operand<'a>(operand: &'a Operand, root: &'a Value, current: &'a Value) -> Option<&'a Value> {
+    match operand {
+        Operand::Current(segments) => get_segments(current, segments),
+        Operand::Root(segments) => get_segments(root, segments),
+        Operand::Literal(value) => Some(value),
+    }
+}
+
+
+fn compare_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64()?.partial_cmp(&r.as_f64()?),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Bool(l), Value::Bool(r)) => Some(l.cmp(r)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => if left == right { Some(Ordering::Equal) } else { None },
+    }
+}
+
+
+fn eval_filter(filter: &Filter, root: &Value, current: &Value) -> bool {
+
+    match filter {
+
+        Filter::Exists(operand) => match resolve_operand(operand, root, current) {
+            Some(Value::Bool(b)) if matches!(operand, Operand::Literal(_)) => *b,
+            Some(_) => true,
+            None => false,
+        },
+
+        Filter::Compare(left, op, right) => {
+            let left = resolve_operand(left, root, current);
+            let right = resolve_operand(right, root, current);
+            match (left, right) {
+                (Some(l), Some(r)) => {
+                    let ordering = compare_values(l, r);
+                    match op {
+                        CompareOp::Eq => ordering == Some(Ordering::Equal),
+                        CompareOp::Ne => ordering != Some(Ordering::Equal),
+                        CompareOp::Lt => ordering == Some(Ordering::Less),
+                        CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                        CompareOp::Gt => ordering == Some(Ordering::Greater),
+                        CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                    }
+                },
+                (None, None) => matches!(op, CompareOp::Eq | CompareOp::Le | CompareOp::Ge),
+                _ => *op == CompareOp::Ne,
+            }
+        },
+
+        Filter::And(terms) => terms.iter().all(|term| eval_filter(term, root, current)),
+        Filter::Or(terms) => terms.iter().any(|term| eval_filter(term, root, current)),
+        Filter::Not(inner) => !eval_filter(inner, root, current),
+
+    }
+
+}
+
+
+// deepest nesting of ! and ( ) accepted, keeps hostile input from overflowing the stack...
+const MAX_DEPTH: usize = 128;
+
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+
+impl Parser<'_> {
+
+    fn error(&self, message: &'static str) -> QueryError {
+        QueryError { expression: self.src.to_string(), position: self.pos, message }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.src[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str, message: &'static str) -> Result<(), QueryError> {
+        self.skip_ws();
+        if self.eat(token) { Ok(()) } else { Err(self.error(message)) }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || ".[]()'\"=!<>&|,@$?*:".contains(c) {
+                break;
+            }
+            self.bump();
+        }
+        if self.pos == start {
+            return Err(self.error("expected a member name"));
+        }
+        Ok(self.src[start..self.pos].to_string())
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, QueryError> {
+        let quote = self.bump().ok_or_else(|| self.error("expected a quoted string"))?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some(c) => out.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) if c == quote => return Ok(out),
+                Some(c) => out.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<Option<i64>, QueryError> {
+        self.skip_ws();
+        let start = self.pos;
+        self.eat("-");
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Ok(None);
+        }
+        let parsed = self.src[start..self.pos].parse::<i64>();
+        parsed.map(Some).map_err(|_| self.error("invalid integer"))
+    }
+
+    fn parse_query(&mut self) -> Result<Vec<Selector>, QueryError> {
+
+        let mut selectors = Vec::new();
+
+        self.skip_ws();
+        self.eat("$");
+
+        if self.peek().is_some_and(|c| c != '.' && c != '[') {
+            if self.eat("*") {
+                selectors.push(Selector::Wildcard);
+            } else {
+                selectors.push(Selector::Child(self.parse_name()?));
+            }
+        }
+
+        while self.pos < self.src.len() {
+            if self.eat("..") {
+                let inner = if self.eat("*") {
+                    Selector::Wildcard
+                } else if self.peek() == Some('[') {
+                    self.parse_bracket()?
+                } else {
+                    Selector::Child(self.parse_name()?)
+                };
+                selectors.push(Selector::Descendant(Box::new(inner)));
+            } else if self.eat(".") {
+                if self.eat("*") {
+                    selectors.push(Selector::Wildcard);
+                } else {
+                    selectors.push(Selector::Child(self.parse_name()?));
+                }
+            } else if self.peek() == Some('[') {
+                selectors.push(self.parse_bracket()?);
+            } else {
+                return Err(self.error("expected '.', '..' or '['"));
+            }
+        }
+
+        Ok(selectors)
+
+    }
+
+    fn parse_bracket(&mut self) -> Result<Selector, QueryError> {
+
+        self.expect("[", "expected '['")?;
+        self.skip_ws();
+
+        let selector = if self.eat("*") {
+            Selector::Wildcard
+        } else if self.eat("?") {
+            self.skip_ws();
+            Selector::Filter(self.parse_or()?)
+        } else if matches!(self.peek(), Some('\'' | '"')) {
+            Selector::Child(self.parse_quoted()?)
+        } else {
+            let start = self.parse_int()?;
+            self.skip_ws();
+            if self.eat(":") {
+                let end = self.parse_int()?;
+                self.skip_ws();
+                let step = if self.eat(":") { self.parse_int()?.unwrap_or(1) } else { 1 };
+                if step == 0 {
+                    return Err(self.error("slice step cannot be zero"));
+                }
+                Selector::Slice(start, end, step)
+            } else {
+                Selector::Index(start.ok_or_else(|| self.error("expected index, slice, '*', quoted name or filter"))?)
+            }
+        };
+
+        self.expect("]", "expected ']'")?;
+        Ok(selector)
+
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, QueryError> {
+        let mut terms = vec![self.parse_and()?];
+        loop {
+            self.skip_ws();
+            if !self.eat("||") {
+                return Ok(if terms.len() == 1 { terms.remove(0) } else { Filter::Or(terms) });
+            }
+            terms.push(self.parse_and()?);
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, QueryError> {
+        let mut terms = vec![self.parse_unary()?];
+        loop {
+            self.skip_ws();
+            if !self.eat("&&") {
+                return Ok(if terms.len() == 1 { terms.remove(0) } else { Filter::And(terms) });
+            }
+            terms.push(self.parse_unary()?);
+        }
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, QueryError>) -> Result<T, QueryError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("expression is nested too deeply"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, QueryError> {
+        self.skip_ws();
+        if self.peek() == Some('!') && !self.src[self.pos..].starts_with("!=") {
+            self.pos += 1;
+            return self.nested(|p| Ok(Filter::Not(Box::new(p.parse_unary()?))));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter, QueryError> {
+
+        self.skip_ws();
+
+        if self.eat("(") {
+            return self.nested(|p| {
+                let inner = p.parse_or()?;
+                p.expect(")", "expected ')'")?;
+                Ok(inner)
+            });
+        }
+
+        let left = self.parse_operand()?;
+        self.skip_ws();
+
+        let op = if self.eat("==") {
+            CompareOp::Eq
+        } else if self.eat("!=") {
+            CompareOp::Ne
+        } else if self.eat("<=") {
+            CompareOp::Le
+        } else if self.eat(">=") {
+            CompareOp::Ge
+        } else if self.eat("<") {
+            CompareOp::Lt
+        } else if self.eat(">") {
+            CompareOp::Gt
+        } else {
+            return Ok(Filter::Exists(left));
+        };
+
+        let right = self.parse_operand()?;
+        Ok(Filter::Compare(left, op, right))
+
+    }
+
+    fn parse_relative(&mut self) -> Result<Vec<PathSegment>, QueryError> {
+        let mut segments = Vec::new();
+        loop {
+            if self.src[self.pos..].starts_with("..") {
+                return Err(self.error("recursive descent is not supported inside filters"));
+            }
+            if self.eat(".") {
+                segments.push(PathSegment::Key(self.parse_name()?));
+            } else if self.eat("[") {
+                self.skip_ws();
+                if matches!(self.peek(), Some('\'' | '"')) {
+                    segments.push(PathSegment::Key(self.parse_quoted()?));
+                } else {
+                    let index = self.parse_int()?
+                        .and_then(|i| usize::try_from(i).ok())
+                        .ok_or_else(|| self.error("expected a positive index"))?;
+                    segments.push(PathSegment::Index(index));
+                }
+                self.expect("]", "expected ']'")?;
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, QueryError> {
+
+        self.skip_ws();
+
+        match self.peek() {
+            Some('@') => {
+                self.pos += 1;
+                Ok(Operand::Current(self.parse_relative()?))
+            },
+            Some('$') => {
+                self.pos += 1;
+                Ok(Operand::Root(self.parse_relative()?))
+            },
+            Some('\'' | '"') => Ok(Operand::Literal(Value::String(self.parse_quoted()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || "-+.eE".contains(c)) {
+                    self.pos += 1;
+                }
+                serde_json::from_str::<Value>(&self.src[start..self.pos])
+                    .ok()
+                    .filter(Value::is_number)
+                    .map(Operand::Literal)
+                    .ok_or_else(|| self.error("invalid number"))
+            },
+            _ => {
+                if self.eat("true") {
+                    Ok(Operand::Literal(Value::Bool(true)))
+                } else if self.eat("false") {
+                    Ok(Operand::Literal(Value::Bool(false)))
+                } else if self.eat("null") {
+                    Ok(Operand::Literal(Value::Null))
+                } else {
+                    Err(self.error("expected '@', '$' or a literal"))
+                }
+            }
+        }
+
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{JsonPath, query};
+
+    #[test]
+    fn jsonpathtests() {
+
+        let doc = json!({
+            "store": {
+                "items": [
+                    {"id": "a", "qty": 1, "price": 10.5},
+                    {"id": "b", "qty": 3, "price": 4},
+                    {"id": "c", "qty": 5, "tags": ["x"]}
+                ],
+                "bike": {"price": 100}
+            }
+        });
+
+        let ids: Vec<_> = query(&doc, "$.store.items[*].id").unwrap().iter().map(|m| m.value.clone()).collect();
+        assert_eq!(ids, vec![json!("a"), json!("b"), json!("c")]);
+
+        let prices = query(&doc, "..price").unwrap();
+        assert_eq!(prices.len(), 3);
+        assert!(prices.iter().any(|m| m.path() == "store.bike.price"));
+
+        let sliced = query(&doc, "store.items[1:3].id").unwrap();
+        assert_eq!(sliced.iter().map(|m| m.path()).collect::<Vec<_>>(), vec!["store.items[1].id", "store.items[2].id"]);
+
+        let filtered = JsonPath::compile("store.items[?(@.qty > 2 && !@.tags)].id").unwrap();
+        assert_eq!(filtered.query_values(&doc), vec![&json!("b")]);
+
+        assert_eq!(JsonPath::compile("store.items[-1].id").unwrap().first(&doc), Some(&json!("c")));
+        assert_eq!(query(&doc, "store.items[?(@.id == 'a' || @.price < 5)]").unwrap().len(), 2);
+
+        assert!(JsonPath::compile("store.items[?(@.qty >)]").is_err());
+        assert!(JsonPath::compile("store.items[0:1:0]").is_err());
+        let huge = JsonPath::compile("store.items[1::9223372036854775807].id").unwrap();
+        assert_eq!(huge.query_values(&doc), vec![&json!("b")]);
+        let huge = JsonPath::compile("store.items[1::-9223372036854775808].id").unwrap();
+        assert_eq!(huge.query_values(&doc), vec![&json!("b")]);
+
+        let deep = format!("a[?({}@.x)]", "!".repeat(200000));
+        assert_eq!(JsonPath::compile(&deep).unwrap_err().message, "expression is nested too deeply");
+        let grouped = format!("a[?({}@.x{})]", "(".repeat(200), ")".repeat(200));
+        assert!(JsonPath::compile(&grouped).is_err());
+        assert!(JsonPath::compile(&format!("a[?({}@.x)]", "!".repeat(100))).is_ok());
+
+        let chain = format!("store.items[?({}@.qty > 2)].id", "@.qty && ".repeat(200000));
+        assert_eq!(JsonPath::compile(&chain).unwrap().query_values(&doc), vec![&json!("b"), &json!("c")]);
+
+    }
+
+}
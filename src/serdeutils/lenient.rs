@@ -0,0 +1,251 @@
+use std::fmt;
+use std::ops::Deref;
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use super::FromLenient;
+
+
+// serde adapters applying the FromLenient coercions to typed structs...
+//
+//   #[serde(deserialize_with = "lenient")]          any FromLenient field (u64, bool, Option<T>, dates...)
+//   #[serde(deserialize_with = "string_or_number")] String field that may arrive as a number
+//   #[serde(deserialize_with = "one_or_many")]      Vec<T> field that may arrive as a single item
+//   #[serde(deserialize_with = "empty_as_none")]    Option<T> field where "" means absent
+//
+// or use the wrapper types directly as field types. Option fields also need #[serde(default)]
+// when the key may be missing.
+
+
+pub fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where D: Deserializer<'de>, T: FromLenient {
+    let value = Value::deserialize(deserializer)?;
+    if value.is_null()
+        && let Some(missing) = T::from_missing() {
+            return Ok(missing);
+        }
+    T::from_lenient(&value).map_err(D::Error::custom)
+}
+
+
+pub fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where D: Deserializer<'de> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => Ok(s),
+        value @ (Value::Number(_) | Value::Bool(_)) => Ok(value.to_string()),
+        value => Err(D::Error::custom(format!("expected string or number, found {value}"))),
+    }
+}
+
+
+pub fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where D: Deserializer<'de>, T: DeserializeOwned {
+    match Value::deserialize(deserializer)? {
+        Value::Array(values) => values
+            .into_iter()
+            .map(|v| serde_json::from_value(v).map_err(D::Error::custom))
+            .collect(),
+        Value::Null => Ok(Vec::new()),
+        value => Ok(vec![serde_json::from_value(value).map_err(D::Error::custom)?]),
+    }
+}
+
+
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where D: Deserializer<'de>, T: DeserializeOwned {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::String(s) if s.trim().is_empty() => Ok(None),
+        value => serde_json::from_value(value).map(Some).map_err(D::Error::custom),
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Lenient<T>(pub T);
+
+pub type LenientU64 = Lenient<u64>;
+pub type LenientI64 = Lenient<i64>;
+pub type LenientU32 = Lenient<u32>;
+pub type LenientF64 = Lenient<f64>;
+pub type LenientBool = Lenient<bool>;
+
+
+impl <T> Deref for Lenient<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+
+impl <T> From<T> for Lenient<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+
+impl <'de, T: FromLenient> Deserialize<'de> for Lenient<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        lenient(deserializer).map(Self)
+    }
+}
+
+
+impl <T: Serialize> Serialize for Lenient<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct StringOrNumber(pub String);
+
+
+impl Deref for StringOrNumber {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+
+impl fmt::Display for StringOrNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+impl <'de> Deserialize<'de> for StringOrNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        string_or_number(deserializer).map(Self)
+    }
+}
+
+
+impl Serialize for StringOrNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OneOrMany<T>(pub Vec<T>);
+
+
+impl <T> Deref for OneOrMany<T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+
+impl <T> IntoIterator for OneOrMany<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+
+impl <'de, T: DeserializeOwned> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        one_or_many(deserializer).map(Self)
+    }
+}
+
+
+impl <T: Serialize> Serialize for OneOrMany<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmptyAsNone<T>(pub Option<T>);
+
+
+impl <T> Default for EmptyAsNone<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+
+impl <T> Deref for EmptyAsNone<T> {
+    type Target = Option<T>;
+    fn deref(&self) -> &Option<T> {
+        &self.0
+    }
+}
+
+
+impl <'de, T: DeserializeOwned> Deserialize<'de> for EmptyAsNone<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        empty_as_none(deserializer).map(Self)
+    }
+}
+
+
+impl <T: Serialize> Serialize for EmptyAsNone<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::{EmptyAsNone, LenientBool, LenientU64, OneOrMany, StringOrNumber, lenient};
+
+    #[derive(Debug, Deserialize)]
+    struct Order {
+        id: StringOrNumber,
+        qty: LenientU64,
+        paid: LenientBool,
+        #[serde(deserialize_with = "lenient")]
+        small: u8,
+        #[serde(default, deserialize_with = "lenient")]
+        discount: Option<f64>,
+        tags: OneOrMany<String>,
+        #[serde(default)]
+        note: EmptyAsNone<String>,
+    }
+
+    #[test]
+    fn lenientserdetests() {
+
+        let order: Order = serde_json::from_value(json!({
+            "id": 1234, "qty": "3", "paid": 1, "small": "8", "tags": "vip", "note": ""
+        })).unwrap();
+
+        assert_eq!(&*order.id, "1234");
+        assert_eq!(*order.qty, 3);
+        assert!(*order.paid);
+        assert_eq!(order.small, 8);
+        assert_eq!(order.discount, None);
+        assert_eq!(order.tags.0, vec!["vip".to_string()]);
+        assert_eq!(*order.note, None);
+
+        let error = serde_json::from_value::<Order>(json!({
+            "id": "a", "qty": 1, "paid": true, "small": 300, "tags": []
+        })).unwrap_err();
+        assert!(error.to_string().contains("out of range for u8"));
+
+    }
+
+}
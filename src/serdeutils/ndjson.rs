@@ -0,0 +1,219 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::httputils::{EncodingWriter, HttpEncoding};
+
+
+// newline delimited json (json lines). the reader yields one result per non-blank line so a bad
+// line is reported with its line number without ending the stream...
+
+
+#[derive(Debug)]
+pub enum NdjsonErrorKind {
+    Io(io::Error),
+    Utf8(std::str::Utf8Error),
+    Parse(serde_json::Error),
+}
+
+
+#[derive(Debug)]
+pub struct NdjsonError {
+    pub line: usize,
+    pub kind: NdjsonErrorKind,
+}
+
+
+impl fmt::Display for NdjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            NdjsonErrorKind::Io(e) => write!(f, "line {}: read error: {e}", self.line),
+            NdjsonErrorKind::Utf8(e) => write!(f, "line {}: invalid utf-8: {e}", self.line),
+            NdjsonErrorKind::Parse(e) => write!(f, "line {}: {e}", self.line),
+        }
+    }
+}
+
+
+impl std::error::Error for NdjsonError {}
+
+
+pub struct NdjsonReader<R: BufRead, T = Value> {
+    reader: R,
+    line: usize,
+    buffer: Vec<u8>,
+    failed_io: bool,
+    _marker: PhantomData<T>,
+}
+
+
+impl <R: BufRead> NdjsonReader<R, Value> {
+
+    pub fn new(reader: R) -> Self {
+        Self::typed(reader)
+    }
+
+}
+
+
+impl <R: BufRead, T: DeserializeOwned> NdjsonReader<R, T> {
+
+    pub fn typed(reader: R) -> Self {
+        Self { reader, line: 0, buffer: Vec::new(), failed_io: false, _marker: PhantomData }
+    }
+
+    // line number of the last line read (1 based)...
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+}
+
+
+impl <R: BufRead, T: DeserializeOwned> Iterator for NdjsonReader<R, T> {
+
+    type Item = Result<(usize, T), NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        // an io error usually repeats forever, stop after reporting it once...
+        if self.failed_io {
+            return None;
+        }
+
+        loop {
+
+            self.buffer.clear();
+            self.line += 1;
+
+            // read bytes rather than lines so bad utf-8 is reported for this line only...
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let text = match std::str::from_utf8(&self.buffer) {
+                        Ok(text) => text.trim(),
+                        Err(e) => return Some(Err(NdjsonError { line: self.line, kind: NdjsonErrorKind::Utf8(e) })),
+                    };
+                    if text.is_empty() {
+                        continue;
+                    }
+                    return Some(serde_json::from_str::<T>(text)
+                        .map(|item| (self.line, item))
+                        .map_err(|e| NdjsonError { line: self.line, kind: NdjsonErrorKind::Parse(e) }));
+                },
+                Err(e) => {
+                    self.failed_io = true;
+                    return Some(Err(NdjsonError { line: self.line, kind: NdjsonErrorKind::Io(e) }));
+                },
+            }
+
+        }
+
+    }
+
+}
+
+
+pub struct NdjsonWriter<W: Write> {
+    inner: EncodingWriter<W>,
+    count: usize,
+}
+
+
+impl <W: Write> NdjsonWriter<W> {
+
+    pub fn new(writer: W) -> Self {
+        Self { inner: EncodingWriter::Identity(writer), count: 0 }
+    }
+
+    pub fn with_encoding(writer: W, encoding: HttpEncoding) -> io::Result<Self> {
+        Ok(Self { inner: EncodingWriter::new(encoding, writer)?, count: 0 })
+    }
+
+    pub fn write<T: Serialize>(&mut self, item: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.inner, item).map_err(io::Error::other)?;
+        self.inner.write_all(b"\n")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn write_all<'a, T: Serialize + 'a>(&mut self, items: impl IntoIterator<Item = &'a T>) -> io::Result<()> {
+        for item in items {
+            self.write(item)?;
+        }
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        self.inner.finish()
+    }
+
+}
+
+
+pub fn to_ndjson<T: Serialize>(items: &[T]) -> io::Result<String> {
+    let mut writer = NdjsonWriter::new(Vec::new());
+    writer.write_all(items)?;
+    String::from_utf8(writer.finish()?).map_err(io::Error::other)
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Cursor;
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use crate::httputils::HttpEncoding;
+
+    use super::{NdjsonErrorKind, NdjsonReader, NdjsonWriter, to_ndjson};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        id: u32,
+    }
+
+    #[test]
+    fn ndjsontests() {
+
+        let input = "{\"id\": 1}\n\n{\"id\": oops}\r\n{\"id\": 3}\n";
+
+        let results: Vec<_> = NdjsonReader::new(Cursor::new(input)).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &(1, json!({"id": 1})));
+        assert!(matches!(results[1], Err(ref e) if e.line == 3 && matches!(e.kind, NdjsonErrorKind::Parse(_))));
+        assert_eq!(results[2].as_ref().unwrap().0, 4);
+
+        let mut bytes = b"{\"id\": 1}\n\"bad ".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xfe]);
+        bytes.extend_from_slice(b"\"\n{\"id\": 3}\n");
+        let results: Vec<_> = NdjsonReader::new(Cursor::new(bytes)).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[1], Err(ref e) if e.line == 2 && matches!(e.kind, NdjsonErrorKind::Utf8(_))));
+        assert_eq!(results[2].as_ref().unwrap(), &(3, json!({"id": 3})));
+
+        let rows: Vec<Row> = NdjsonReader::typed(Cursor::new(input)).filter_map(Result::ok).map(|(_, r)| r).collect();
+        assert_eq!(rows, vec![Row { id: 1 }, Row { id: 3 }]);
+
+        assert_eq!(to_ndjson(&[json!({"a": 1}), json!([2])]).unwrap(), "{\"a\":1}\n[2]\n");
+
+        let mut writer = NdjsonWriter::with_encoding(Vec::new(), HttpEncoding::Gzip).unwrap();
+        writer.write(&json!({"id": 1})).unwrap();
+        assert_eq!(writer.count(), 1);
+        let compressed = writer.finish().unwrap();
+        assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+
+    }
+
+}
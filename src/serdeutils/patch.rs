@@ -0,0 +1,349 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+
+// RFC 7396 merge patches and RFC 6902 json patches. json patch paths are json pointers
+// ("/items/0/name", "~1" for '/', "~0" for '~', "-" appends to an array).
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    InvalidPointer(String),
+    PathNotFound(String),
+    InvalidIndex(String),
+    MoveIntoChild { from: String, path: String },
+    TestFailed(String),
+}
+
+
+#[derive(Debug)]
+pub enum PatchError {
+    Parse(serde_json::Error),
+    Operation { index: usize, kind: PatchErrorKind },
+}
+
+
+impl fmt::Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPointer(path) => write!(f, "invalid json pointer '{path}'"),
+            Self::PathNotFound(path) => write!(f, "path '{path}' does not exist"),
+            Self::InvalidIndex(path) => write!(f, "invalid array index in '{path}'"),
+            Self::MoveIntoChild { from, path } => write!(f, "cannot move '{from}' into its own child '{path}'"),
+            Self::TestFailed(path) => write!(f, "test failed at '{path}'"),
+        }
+    }
+}
+
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "invalid patch document: {e}"),
+            Self::Operation { index, kind } => write!(f, "patch operation {index} failed: {kind}"),
+        }
+    }
+}
+
+
+impl std::error::Error for PatchError {}
+
+
+// merge patch...
+
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+
+    let Value::Object(patch_map) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+
+    if let Value::Object(target_map) = target {
+        for (key, value) in patch_map {
+            if value.is_null() {
+                target_map.remove(key);
+            } else {
+                merge_patch(target_map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+
+}
+
+
+// builds the merge patch that turns source into target, note null values in target
+// cannot be expressed (RFC 7396 uses null for removal)...
+pub fn merge_patch_diff(source: &Value, target: &Value) -> Value {
+
+    match (source, target) {
+
+        (Value::Object(source_map), Value::Object(target_map)) => {
+            let mut out = Map::new();
+            for key in source_map.keys() {
+                if !target_map.contains_key(key) {
+                    out.insert(key.clone(), Value::Null);
+                }
+            }
+            for (key, value) in target_map {
+                match source_map.get(key) {
+                    Some(existing) if existing == value => {},
+                    Some(existing) => {
+                        out.insert(key.clone(), merge_patch_diff(existing, value));
+                    },
+                    None => {
+                        out.insert(key.clone(), merge_patch_diff(&Value::Null, value));
+                    },
+                }
+            }
+            Value::Object(out)
+        },
+
+        (_, Value::Object(_)) => merge_patch_diff(&Value::Object(Map::new()), target),
+
+        _ => target.clone(),
+
+    }
+
+}
+
+
+// json patch...
+
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchErrorKind> {
+
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PatchErrorKind::InvalidPointer(pointer.to_string()));
+    };
+
+    rest.split('/')
+        .map(|token| {
+            if token.replace("~0", "").replace("~1", "").contains('~') {
+                Err(PatchErrorKind::InvalidPointer(pointer.to_string()))
+            } else {
+                Ok(token.replace("~1", "/").replace("~0", "~"))
+            }
+        })
+        .collect()
+
+}
+
+
+fn array_index(token: &str, len: usize, allow_end: bool, pointer: &str) -> Result<usize, PatchErrorKind> {
+    if allow_end && token == "-" {
+        return Ok(len);
+    }
+    let valid = !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) && (token == "0" || !token.starts_with('0'));
+    let index = token.parse::<usize>().ok().filter(|_| valid).ok_or_else(|| PatchErrorKind::InvalidIndex(pointer.to_string()))?;
+    let limit = if allow_end { len + 1 } else { len };
+    if index < limit { Ok(index) } else { Err(PatchErrorKind::PathNotFound(pointer.to_string())) }
+}
+
+
+fn pointer_get<'a>(doc: &'a Value, tokens: &[String], pointer: &str) -> Result<&'a Value, PatchErrorKind> {
+    tokens.iter().try_fold(doc, |current, token| match current {
+        Value::Object(map) => map.get(token).ok_or_else(|| PatchErrorKind::PathNotFound(pointer.to_string())),
+        Value::Array(values) => Ok(&values[array_index(token, values.len(), false, pointer)?]),
+        _ => Err(PatchErrorKind::PathNotFound(pointer.to_string())),
+    })
+}
+
+
+fn pointer_get_mut<'a>(doc: &'a mut Value, tokens: &[String], pointer: &str) -> Result<&'a mut Value, PatchErrorKind> {
+    tokens.iter().try_fold(doc, |current, token| match current {
+        Value::Object(map) => map.get_mut(token).ok_or_else(|| PatchErrorKind::PathNotFound(pointer.to_string())),
+        Value::Array(values) => {
+            let index = array_index(token, values.len(), false, pointer)?;
+            Ok(&mut values[index])
+        },
+        _ => Err(PatchErrorKind::PathNotFound(pointer.to_string())),
+    })
+}
+
+
+fn pointer_add(doc: &mut Value, pointer: &str, value: Value) -> Result<(), PatchErrorKind> {
+
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    match pointer_get_mut(doc, parents, pointer)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        },
+        Value::Array(values) => {
+            let index = array_index(last, values.len(), true, pointer)?;
+            values.insert(index, value);
+            Ok(())
+        },
+        _ => Err(PatchErrorKind::PathNotFound(pointer.to_string())),
+    }
+
+}
+
+
+fn pointer_remove(doc: &mut Value, pointer: &str) -> Result<Value, PatchErrorKind> {
+
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        return Ok(std::mem::take(doc));
+    };
+
+    match pointer_get_mut(doc, parents, pointer)? {
+        Value::Object(map) => map.remove(last).ok_or_else(|| PatchErrorKind::PathNotFound(pointer.to_string())),
+        Value::Array(values) => {
+            let index = array_index(last, values.len(), false, pointer)?;
+            Ok(values.remove(index))
+        },
+        _ => Err(PatchErrorKind::PathNotFound(pointer.to_string())),
+    }
+
+}
+
+
+fn apply_operation(doc: &mut Value, operation: &PatchOperation) -> Result<(), PatchErrorKind> {
+
+    match operation {
+
+        PatchOperation::Add { path, value } => pointer_add(doc, path, value.clone()),
+
+        PatchOperation::Remove { path } => pointer_remove(doc, path).map(|_| ()),
+
+        PatchOperation::Replace { path, value } => {
+            let tokens = parse_pointer(path)?;
+            *pointer_get_mut(doc, &tokens, path)? = value.clone();
+            Ok(())
+        },
+
+        PatchOperation::Move { from, path } => {
+            if from == path {
+                let tokens = parse_pointer(from)?;
+                return pointer_get(doc, &tokens, from).map(|_| ());
+            }
+            if path.starts_with(&format!("{from}/")) {
+                return Err(PatchErrorKind::MoveIntoChild { from: from.clone(), path: path.clone() });
+            }
+            let value = pointer_remove(doc, from)?;
+            pointer_add(doc, path, value)
+        },
+
+        PatchOperation::Copy { from, path } => {
+            let tokens = parse_pointer(from)?;
+            let value = pointer_get(doc, &tokens, from)?.clone();
+            pointer_add(doc, path, value)
+        },
+
+        PatchOperation::Test { path, value } => {
+            let tokens = parse_pointer(path)?;
+            if pointer_get(doc, &tokens, path)? == value {
+                Ok(())
+            } else {
+                Err(PatchErrorKind::TestFailed(path.clone()))
+            }
+        },
+
+    }
+
+}
+
+
+// applies all operations or none of them...
+pub fn apply_patch(doc: &mut Value, operations: &[PatchOperation]) -> Result<(), PatchError> {
+    let mut working = doc.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut working, operation).map_err(|kind| PatchError::Operation { index, kind })?;
+    }
+    *doc = working;
+    Ok(())
+}
+
+
+pub fn apply_patch_value(doc: &mut Value, patch: &Value) -> Result<(), PatchError> {
+    let operations: Vec<PatchOperation> = serde_json::from_value(patch.clone()).map_err(PatchError::Parse)?;
+    apply_patch(doc, &operations)
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{PatchError, PatchErrorKind, apply_patch_value, merge_patch, merge_patch_diff};
+
+    #[test]
+    fn mergepatchtests() {
+
+        let mut doc = json!({"a": "b", "c": {"d": "e", "f": "g"}});
+        merge_patch(&mut doc, &json!({"a": "z", "c": {"f": null}}));
+        assert_eq!(doc, json!({"a": "z", "c": {"d": "e"}}));
+
+        let source = json!({"title": "Hello", "author": {"given": "John", "family": "Doe"}, "tags": ["a"]});
+        let target = json!({"title": "Hi", "author": {"given": "John"}, "tags": ["b"], "extra": 1});
+        let diff = merge_patch_diff(&source, &target);
+        assert_eq!(diff, json!({"title": "Hi", "author": {"family": null}, "tags": ["b"], "extra": 1}));
+
+        let mut patched = source.clone();
+        merge_patch(&mut patched, &diff);
+        assert_eq!(patched, target);
+
+    }
+
+    #[test]
+    fn jsonpatchtests() {
+
+        let mut doc = json!({"foo": ["bar", "baz"], "a/b": 1, "obj": {"x": 1}});
+
+        apply_patch_value(&mut doc, &json!([
+            {"op": "add", "path": "/foo/1", "value": "qux"},
+            {"op": "add", "path": "/foo/-", "value": "end"},
+            {"op": "remove", "path": "/a~1b"},
+            {"op": "replace", "path": "/obj/x", "value": 2},
+            {"op": "copy", "from": "/obj", "path": "/copied"},
+            {"op": "move", "from": "/foo/0", "path": "/first"},
+            {"op": "test", "path": "/copied/x", "value": 2}
+        ])).unwrap();
+
+        assert_eq!(doc, json!({"foo": ["qux", "baz", "end"], "obj": {"x": 2}, "copied": {"x": 2}, "first": "bar"}));
+
+        let before = doc.clone();
+        let result = apply_patch_value(&mut doc, &json!([
+            {"op": "remove", "path": "/first"},
+            {"op": "test", "path": "/obj/x", "value": 3}
+        ]));
+
+        assert!(matches!(result, Err(PatchError::Operation { index: 1, kind: PatchErrorKind::TestFailed(_) })));
+        assert_eq!(doc, before);
+
+        assert!(matches!(apply_patch_value(&mut doc, &json!([{"op": "move", "from": "/obj", "path": "/obj/y"}])),
+            Err(PatchError::Operation { kind: PatchErrorKind::MoveIntoChild { .. }, .. })));
+        assert!(matches!(apply_patch_value(&mut doc, &json!([{"op": "jump"}])), Err(PatchError::Parse(_))));
+
+    }
+
+}
@@ -0,0 +1,277 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use super::{JsonPath, QueryError, get_segments_mut, remove_segments};
+
+
+// masking of sensitive values before payloads are logged. rules match by key name (anywhere in
+// the document, case insensitive), by JSONPath expression, or by a regex over string values,
+// and apply in the order they were added, so later rules see the output of earlier ones...
+//
+//   let redactor = Redactor::new()
+//       .key("password", RedactAction::Remove)
+//       .path("$.card.number", RedactAction::Partial { keep_first: 0, keep_last: 4 })?
+//       .pattern(EMAIL_PATTERN, RedactAction::Mask)?;
+//   redact(&mut body, &redactor);
+
+
+pub const MASK: &str = "****";
+
+pub const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}";
+pub const CARD_PATTERN: &str = r"\b(?:\d[ \-]?){12,18}\d\b";
+pub const BEARER_PATTERN: &str = r"(?i)bearer\s+[A-Za-z0-9._~+/\-]+=*";
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedactAction {
+    Remove,
+    Mask,
+    Partial { keep_first: usize, keep_last: usize },
+    Replace(Value),
+}
+
+
+#[derive(Debug, Clone)]
+pub enum RedactMatch {
+    Key(String),
+    Path(JsonPath),
+    Pattern(Regex),
+}
+
+
+#[derive(Debug, Clone)]
+pub struct RedactRule {
+    pub matcher: RedactMatch,
+    pub action: RedactAction,
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    pub rules: Vec<RedactRule>,
+}
+
+
+impl Redactor {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // common secrets and pii: credential keys are removed, emails / cards / bearer tokens masked...
+    pub fn pii() -> Self {
+        let mut redactor = Self::new();
+        for key in ["password", "passwd", "secret", "token", "access_token", "refresh_token", "api_key", "apikey", "authorization", "cookie", "cvv"] {
+            redactor = redactor.key(key, RedactAction::Mask);
+        }
+        redactor.rules.push(RedactRule { matcher: RedactMatch::Pattern(Regex::new(CARD_PATTERN).unwrap()), action: RedactAction::Partial { keep_first: 0, keep_last: 4 } });
+        redactor.rules.push(RedactRule { matcher: RedactMatch::Pattern(Regex::new(EMAIL_PATTERN).unwrap()), action: RedactAction::Mask });
+        redactor.rules.push(RedactRule { matcher: RedactMatch::Pattern(Regex::new(BEARER_PATTERN).unwrap()), action: RedactAction::Mask });
+        redactor
+    }
+
+    pub fn key(mut self, key: &str, action: RedactAction) -> Self {
+        self.rules.push(RedactRule { matcher: RedactMatch::Key(key.to_lowercase()), action });
+        self
+    }
+
+    pub fn path(mut self, expression: &str, action: RedactAction) -> Result<Self, QueryError> {
+        self.rules.push(RedactRule { matcher: RedactMatch::Path(JsonPath::compile(expression)?), action });
+        Ok(self)
+    }
+
+    pub fn pattern(mut self, pattern: &str, action: RedactAction) -> Result<Self, regex::Error> {
+        self.rules.push(RedactRule { matcher: RedactMatch::Pattern(Regex::new(pattern)?), action });
+        Ok(self)
+    }
+
+}
+
+
+// masks all but the first / last characters, strings too short to keep anything are fully masked...
+pub fn mask_partial(stringin: &str, keep_first: usize, keep_last: usize) -> String {
+    let chars: Vec<char> = stringin.chars().collect();
+    if chars.len() <= keep_first + keep_last {
+        return "*".repeat(chars.len().max(MASK.len()));
+    }
+    let hidden = chars.len() - keep_first - keep_last;
+    let mut out: String = chars[..keep_first].iter().collect();
+    out.push_str(&"*".repeat(hidden));
+    out.extend(&chars[chars.len() - keep_last..]);
+    out
+}
+
+
+fn masked_value(value: &Value, action: &RedactAction) -> Value {
+    match action {
+        RedactAction::Remove | RedactAction::Mask => Value::from(MASK),
+        RedactAction::Partial { keep_first, keep_last } => {
+            let text = match value {
+                Value::String(s) => s.clone(),
+                Value::Null => return Value::Null,
+                _ => value.to_string(),
+            };
+            Value::from(mask_partial(&text, *keep_first, *keep_last))
+        },
+        RedactAction::Replace(replacement) => replacement.clone(),
+    }
+}
+
+
+fn redact_keys(value: &mut Value, key: &str, action: &RedactAction) {
+    match value {
+        Value::Object(map) => redact_map_keys(map, key, action),
+        Value::Array(values) => {
+            for item in values {
+                redact_keys(item, key, action);
+            }
+        },
+        _ => {}
+    }
+}
+
+
+fn redact_map_keys(map: &mut Map<String, Value>, key: &str, action: &RedactAction) {
+    if *action == RedactAction::Remove {
+        map.retain(|k, _| k.to_lowercase() != key);
+    }
+    for (k, item) in map.iter_mut() {
+        if k.to_lowercase() == key {
+            *item = masked_value(item, action);
+        } else {
+            redact_keys(item, key, action);
+        }
+    }
+}
+
+
+fn redact_pattern(value: &mut Value, pattern: &Regex, action: &RedactAction) {
+    match value {
+        Value::String(s) => {
+            if !pattern.is_match(s) {
+                return;
+            }
+            if *action == RedactAction::Remove {
+                *value = Value::Null;
+                return;
+            }
+            let replaced = pattern.replace_all(s, |caps: &regex::Captures| {
+                match masked_value(&Value::from(&caps[0]), action) {
+                    Value::String(masked) => masked,
+                    other => other.to_string(),
+                }
+            });
+            *s = replaced.into_owned();
+        },
+        Value::Array(values) => {
+            for item in values {
+                redact_pattern(item, pattern, action);
+            }
+        },
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                redact_pattern(item, pattern, action);
+            }
+        },
+        _ => {}
+    }
+}
+
+
+pub fn redact(value: &mut Value, redactor: &Redactor) {
+
+    for rule in &redactor.rules {
+
+        match &rule.matcher {
+
+            RedactMatch::Key(key) => redact_keys(value, key, &rule.action),
+
+            RedactMatch::Path(path) => {
+                let mut targets: Vec<_> = path.query(value).into_iter().map(|m| m.segments).collect();
+                // deepest / highest indexes first so removals do not shift later targets...
+                targets.sort();
+                targets.dedup();
+                for segments in targets.into_iter().rev() {
+                    if rule.action == RedactAction::Remove {
+                        remove_segments(value, &segments);
+                    } else if let Some(target) = get_segments_mut(value, &segments) {
+                        *target = masked_value(target, &rule.action);
+                    }
+                }
+            },
+
+            RedactMatch::Pattern(pattern) => redact_pattern(value, pattern, &rule.action),
+
+        }
+
+    }
+
+}
+
+
+pub fn redacted(value: &Value, redactor: &Redactor) -> Value {
+    let mut out = value.clone();
+    redact(&mut out, redactor);
+    out
+}
+
+
+pub fn redact_map(map: &Map<String, Value>, redactor: &Redactor) -> Map<String, Value> {
+    match redacted(&Value::Object(map.clone()), redactor) {
+        Value::Object(out) => out,
+        _ => Map::new(),
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{RedactAction, Redactor, mask_partial, redact, redacted};
+
+    #[test]
+    fn redacttests() {
+
+        let mut body = json!({
+            "user": {"email": "sam@example.com", "Password": "hunter2"},
+            "card": {"number": "4111111111111111", "cvv": 123},
+            "items": [{"secret": 1}, {"secret": 2, "keep": true}],
+            "note": "call sam@example.com about card 4111 1111 1111 1111",
+            "headers": {"authorization": "Bearer abc.def"}
+        });
+
+        redact(&mut body, &Redactor::pii());
+
+        assert_eq!(body["user"]["Password"], json!("****"));
+        assert_eq!(body["user"]["email"], json!("****"));
+        assert_eq!(body["card"]["number"], json!("************1111"));
+        assert_eq!(body["card"]["cvv"], json!("****"));
+        assert_eq!(body["note"], json!("call **** about card ***************1111"));
+        assert_eq!(body["headers"]["authorization"], json!("****"));
+
+        let custom = Redactor::new()
+            .key("secret", RedactAction::Remove)
+            .path("$.items[?(@.keep)]", RedactAction::Remove).unwrap()
+            .path("card.number", RedactAction::Partial { keep_first: 4, keep_last: 0 }).unwrap();
+
+        let out = redacted(&json!({"items": [{"secret": 1, "keep": true}, {"secret": 2}], "card": {"number": 4111222233334444u64}}), &custom);
+        assert_eq!(out, json!({"items": [{}], "card": {"number": "4111************"}}));
+
+        let body = json!({"pin": "1234", "note": "7"});
+        let key_first = Redactor::new()
+            .key("pin", RedactAction::Replace(json!("x-1")))
+            .pattern(r"\d", RedactAction::Replace(json!("#"))).unwrap();
+        assert_eq!(redacted(&body, &key_first), json!({"pin": "x-#", "note": "#"}));
+        let pattern_first = Redactor::new()
+            .pattern(r"\d", RedactAction::Replace(json!("#"))).unwrap()
+            .key("pin", RedactAction::Replace(json!("x-1")));
+        assert_eq!(redacted(&body, &pattern_first), json!({"pin": "x-1", "note": "#"}));
+
+        assert_eq!(mask_partial("ab", 0, 4), "****");
+
+    }
+
+}
@@ -0,0 +1,435 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{PathSegment, format_path};
+
+
+// a small subset of json schema for validating incoming payloads, compiled once from a Value:
+//
+//   {
+//     "type": "object",
+//     "required": ["email", "age"],
+//     "additionalProperties": false,
+//     "properties": {
+//       "email": {"type": "string", "pattern": "^[^@]+@[^@]+$", "maxLength": 254},
+//       "age": {"type": "integer", "minimum": 18},
+//       "role": {"enum": ["admin", "user"]},
+//       "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 10}
+//     }
+//   }
+//
+// "type" may also be a list (["string", "null"]). validation reports every violation.
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaType {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+
+impl SchemaType {
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "null" => Some(Self::Null),
+            "boolean" => Some(Self::Boolean),
+            "integer" => Some(Self::Integer),
+            "number" => Some(Self::Number),
+            "string" => Some(Self::String),
+            "array" => Some(Self::Array),
+            "object" => Some(Self::Object),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Array => "array",
+            Self::Object => "object",
+        }
+    }
+
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Boolean,
+            Value::Number(n) if n.is_i64() || n.is_u64() => Self::Integer,
+            Value::Number(_) => Self::Number,
+            Value::String(_) => Self::String,
+            Value::Array(_) => Self::Array,
+            Value::Object(_) => Self::Object,
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Integer, Value::Number(n)) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+            (Self::Number, Value::Number(_)) => true,
+            _ => *self == Self::of(value),
+        }
+    }
+
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid schema at '{}': {}", self.path, self.message)
+    }
+}
+
+
+impl std::error::Error for SchemaError {}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    Type { expected: Vec<SchemaType>, found: SchemaType },
+    Required(String),
+    AdditionalProperty(String),
+    Minimum(f64),
+    Maximum(f64),
+    MinLength(usize),
+    MaxLength(usize),
+    MinItems(usize),
+    MaxItems(usize),
+    Pattern(String),
+    Enum,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "<root>" } else { &self.path };
+        match &self.kind {
+            ViolationKind::Type { expected, found } => {
+                let names: Vec<_> = expected.iter().map(SchemaType::name).collect();
+                write!(f, "{path}: expected {}, found {}", names.join(" or "), found.name())
+            },
+            ViolationKind::Required(key) => write!(f, "{path}: missing required key '{key}'"),
+            ViolationKind::AdditionalProperty(key) => write!(f, "{path}: unexpected key '{key}'"),
+            ViolationKind::Minimum(min) => write!(f, "{path}: must be at least {min}"),
+            ViolationKind::Maximum(max) => write!(f, "{path}: must be at most {max}"),
+            ViolationKind::MinLength(min) => write!(f, "{path}: must be at least {min} characters"),
+            ViolationKind::MaxLength(max) => write!(f, "{path}: must be at most {max} characters"),
+            ViolationKind::MinItems(min) => write!(f, "{path}: must have at least {min} items"),
+            ViolationKind::MaxItems(max) => write!(f, "{path}: must have at most {max} items"),
+            ViolationKind::Pattern(pattern) => write!(f, "{path}: does not match '{pattern}'"),
+            ViolationKind::Enum => write!(f, "{path}: is not one of the allowed values"),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub types: Vec<SchemaType>,
+    pub required: Vec<String>,
+    pub properties: BTreeMap<String, Schema>,
+    pub additional_properties: bool,
+    pub items: Option<Box<Schema>>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_items: Option<usize>,
+    pub max_items: Option<usize>,
+    pub pattern: Option<Regex>,
+    pub enum_values: Vec<Value>,
+}
+
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self {
+            types: Vec::new(),
+            required: Vec::new(),
+            properties: BTreeMap::new(),
+            additional_properties: true,
+            items: None,
+            minimum: None,
+            maximum: None,
+            min_length: None,
+            max_length: None,
+            min_items: None,
+            max_items: None,
+            pattern: None,
+            enum_values: Vec::new(),
+        }
+    }
+}
+
+
+impl Schema {
+
+    pub fn from_value(definition: &Value) -> Result<Self, SchemaError> {
+        compile(definition, &mut Vec::new())
+    }
+
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<Violation>> {
+        let violations = self.violations(value);
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    pub fn violations(&self, value: &Value) -> Vec<Violation> {
+        let mut out = Vec::new();
+        check(self, value, &mut Vec::new(), &mut out);
+        out
+    }
+
+}
+
+
+impl FromStr for Schema {
+
+    type Err = SchemaError;
+
+    fn from_str(definition: &str) -> Result<Self, SchemaError> {
+        let parsed: Value = serde_json::from_str(definition).map_err(|e| SchemaError { path: String::new(), message: e.to_string() })?;
+        Self::from_value(&parsed)
+    }
+
+}
+
+
+fn compile(definition: &Value, path: &mut Vec<PathSegment>) -> Result<Schema, SchemaError> {
+
+    let error = |path: &[PathSegment], message: String| SchemaError { path: format_path(path), message };
+
+    let Some(object) = definition.as_object() else {
+        return Err(error(path, "schema must be an object".to_string()));
+    };
+
+    let mut schema = Schema::default();
+
+    let size = |key: &str| -> Result<Option<usize>, SchemaError> {
+        match object.get(key) {
+            None => Ok(None),
+            Some(v) => v.as_u64().map(|n| Some(n as usize)).ok_or_else(|| error(path, format!("'{key}' must be a positive integer"))),
+        }
+    };
+    let number = |key: &str| -> Result<Option<f64>, SchemaError> {
+        match object.get(key) {
+            None => Ok(None),
+            Some(v) => v.as_f64().map(Some).ok_or_else(|| error(path, format!("'{key}' must be a number"))),
+        }
+    };
+
+    schema.min_length = size("minLength")?;
+    schema.max_length = size("maxLength")?;
+    schema.min_items = size("minItems")?;
+    schema.max_items = size("maxItems")?;
+    schema.minimum = number("minimum")?;
+    schema.maximum = number("maximum")?;
+
+    if let Some(types) = object.get("type") {
+        let names: Vec<&Value> = match types {
+            Value::Array(list) => list.iter().collect(),
+            other => vec![other],
+        };
+        for name in names {
+            let parsed = name.as_str().and_then(SchemaType::from_name)
+                .ok_or_else(|| error(path, format!("unknown type {name}")))?;
+            schema.types.push(parsed);
+        }
+    }
+
+    if let Some(required) = object.get("required") {
+        let list = required.as_array().ok_or_else(|| error(path, "'required' must be an array".to_string()))?;
+        for key in list {
+            let key = key.as_str().ok_or_else(|| error(path, "'required' entries must be strings".to_string()))?;
+            schema.required.push(key.to_string());
+        }
+    }
+
+    if let Some(additional) = object.get("additionalProperties") {
+        schema.additional_properties = additional.as_bool().ok_or_else(|| error(path, "'additionalProperties' must be a boolean".to_string()))?;
+    }
+
+    if let Some(pattern) = object.get("pattern") {
+        let pattern = pattern.as_str().ok_or_else(|| error(path, "'pattern' must be a string".to_string()))?;
+        schema.pattern = Some(Regex::new(pattern).map_err(|e| error(path, e.to_string()))?);
+    }
+
+    if let Some(values) = object.get("enum") {
+        schema.enum_values = values.as_array().ok_or_else(|| error(path, "'enum' must be an array".to_string()))?.clone();
+    }
+
+    if let Some(properties) = object.get("properties") {
+        let properties = properties.as_object().ok_or_else(|| error(path, "'properties' must be an object".to_string()))?;
+        for (key, definition) in properties {
+            path.push(PathSegment::Key(key.clone()));
+            let compiled = compile(definition, path);
+            path.pop();
+            schema.properties.insert(key.clone(), compiled?);
+        }
+    }
+
+    if let Some(items) = object.get("items") {
+        path.push(PathSegment::Index(0));
+        let compiled = compile(items, path);
+        path.pop();
+        schema.items = Some(Box::new(compiled?));
+    }
+
+    Ok(schema)
+
+}
+
+
+fn push(out: &mut Vec<Violation>, path: &[PathSegment], kind: ViolationKind) {
+    out.push(Violation { path: format_path(path), kind });
+}
+
+
+fn check(schema: &Schema, value: &Value, path: &mut Vec<PathSegment>, out: &mut Vec<Violation>) {
+
+    if !schema.types.is_empty() && !schema.types.iter().any(|t| t.matches(value)) {
+        push(out, path, ViolationKind::Type { expected: schema.types.clone(), found: SchemaType::of(value) });
+        return;
+    }
+
+    if !schema.enum_values.is_empty() && !schema.enum_values.contains(value) {
+        push(out, path, ViolationKind::Enum);
+    }
+
+    match value {
+
+        Value::Number(n) => {
+            let number = n.as_f64().unwrap_or(0.0);
+            if let Some(min) = schema.minimum && number < min {
+                push(out, path, ViolationKind::Minimum(min));
+            }
+            if let Some(max) = schema.maximum && number > max {
+                push(out, path, ViolationKind::Maximum(max));
+            }
+        },
+
+        Value::String(s) => {
+            let length = s.chars().count();
+            if let Some(min) = schema.min_length && length < min {
+                push(out, path, ViolationKind::MinLength(min));
+            }
+            if let Some(max) = schema.max_length && length > max {
+                push(out, path, ViolationKind::MaxLength(max));
+            }
+            if let Some(pattern) = &schema.pattern && !pattern.is_match(s) {
+                push(out, path, ViolationKind::Pattern(pattern.as_str().to_string()));
+            }
+        },
+
+        Value::Array(values) => {
+            if let Some(min) = schema.min_items && values.len() < min {
+                push(out, path, ViolationKind::MinItems(min));
+            }
+            if let Some(max) = schema.max_items && values.len() > max {
+                push(out, path, ViolationKind::MaxItems(max));
+            }
+            if let Some(items) = &schema.items {
+                for (index, item) in values.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    check(items, item, path, out);
+                    path.pop();
+                }
+            }
+        },
+
+        Value::Object(map) => {
+            for key in &schema.required {
+                if !map.contains_key(key) {
+                    push(out, path, ViolationKind::Required(key.clone()));
+                }
+            }
+            for (key, item) in map {
+                match schema.properties.get(key) {
+                    Some(child) => {
+                        path.push(PathSegment::Key(key.clone()));
+                        check(child, item, path, out);
+                        path.pop();
+                    },
+                    None if !schema.additional_properties => push(out, path, ViolationKind::AdditionalProperty(key.clone())),
+                    None => {},
+                }
+            }
+        },
+
+        _ => {}
+
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{Schema, ViolationKind};
+
+    #[test]
+    fn schematests() {
+
+        let schema = Schema::from_value(&json!({
+            "type": "object",
+            "required": ["email", "age"],
+            "additionalProperties": false,
+            "properties": {
+                "email": {"type": "string", "pattern": "^[^@]+@[^@]+$"},
+                "age": {"type": "integer", "minimum": 18},
+                "role": {"enum": ["admin", "user"]},
+                "tags": {"type": "array", "maxItems": 2, "items": {"type": "string", "minLength": 1}},
+                "nickname": {"type": ["string", "null"]}
+            }
+        })).unwrap();
+
+        assert!(schema.validate(&json!({"email": "a@b", "age": 30, "tags": ["x"], "nickname": null})).is_ok());
+
+        let violations = schema.validate(&json!({
+            "email": "nope", "age": 12.5, "role": "root", "tags": ["", 1, "z"], "extra": 1
+        })).unwrap_err();
+
+        let paths: Vec<_> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, vec!["age", "email", "", "role", "tags", "tags[0]", "tags[1]"]);
+        assert!(matches!(violations[2].kind, ViolationKind::AdditionalProperty(_)));
+        assert_eq!(violations[6].to_string(), "tags[1]: expected string, found integer");
+
+        let missing = schema.validate(&json!({})).unwrap_err();
+        assert_eq!(missing.len(), 2);
+
+        assert!(Schema::from_value(&json!({"type": "text"})).is_err());
+        assert!(Schema::from_value(&json!({"properties": {"a": {"pattern": "("}}})).is_err());
+
+    }
+
+}
@@ -0,0 +1,474 @@
+use std::fmt;
+
+use chrono::DateTime;
+use chrono::format::{Item, StrftimeItems};
+use serde_json::Value;
+
+use super::{PathSegment, get_segments, parse_path};
+
+
+// mustache style templates rendered against a Value...
+//
+//   {{user.name}}                  html escaped lookup, same path syntax as recurse_value
+//   {{{body}}} or {{& body}}       raw (unescaped) output
+//   {{#items}}..{{/items}}         loop over arrays, or render once when the value is truthy
+//   {{^items}}..{{/items}}         render when missing / falsy (null, false, 0, "", [] or {})
+//   {{.}} and {{@index}}           current item and loop index inside sections
+//   {{name | upper | default:"Guest"}}
+//   {{! comment }}
+//
+// filters: upper, lower, trim, capitalize, length, json, truncate:N, default:"x", date:"%d/%m/%Y"
+// (date accepts epoch seconds or rfc3339 strings).
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub position: usize,
+    pub message: String,
+}
+
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "template error at position {}: {}", self.position, self.message)
+    }
+}
+
+
+impl std::error::Error for TemplateError {}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Lookup {
+    Current,
+    Index,
+    Path(Vec<PathSegment>),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    Capitalize,
+    Length,
+    Json,
+    Truncate(usize),
+    Default(String),
+    Date(String),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var { lookup: Lookup, filters: Vec<Filter>, escape: bool },
+    Section { lookup: Lookup, inverted: bool, children: Vec<Node> },
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+
+struct Frame<'a> {
+    value: &'a Value,
+    index: Option<usize>,
+}
+
+
+impl Template {
+
+    pub fn compile(source: &str) -> Result<Self, TemplateError> {
+
+        // stack of (open tag name, position, parsed lookup, inverted, nodes collected so far)
+        let mut stack: Vec<(String, usize, Lookup, bool, Vec<Node>)> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut rest = source;
+        let mut offset = 0;
+
+        while let Some(open) = rest.find("{{") {
+
+            if open > 0 {
+                nodes.push(Node::Text(rest[..open].to_string()));
+            }
+
+            let position = offset + open;
+            let triple = rest[open..].starts_with("{{{");
+            let (start, closer) = if triple { (open + 3, "}}}") } else { (open + 2, "}}") };
+
+            let close = rest[start..].find(closer).ok_or_else(|| TemplateError {
+                position,
+                message: "unclosed tag".to_string(),
+            })? + start;
+
+            let tag = rest[start..close].trim();
+            let consumed = close + closer.len();
+
+            if triple {
+                let (lookup, filters) = parse_expression(tag, position)?;
+                nodes.push(Node::Var { lookup, filters, escape: false });
+            } else if tag.starts_with('!') {
+                // comment, nothing to render
+            } else if let Some(name) = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^')) {
+                let name = name.trim();
+                let lookup = parse_lookup(name, position)?;
+                stack.push((name.to_string(), position, lookup, tag.starts_with('^'), std::mem::take(&mut nodes)));
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                let (open_name, _, lookup, inverted, parent) = stack.pop().ok_or_else(|| TemplateError {
+                    position,
+                    message: format!("closing tag '{name}' without an open section"),
+                })?;
+                if open_name != name {
+                    return Err(TemplateError {
+                        position,
+                        message: format!("closing tag '{name}' does not match open section '{open_name}'"),
+                    });
+                }
+                let children = std::mem::replace(&mut nodes, parent);
+                nodes.push(Node::Section { lookup, inverted, children });
+            } else if let Some(raw) = tag.strip_prefix('&') {
+                let (lookup, filters) = parse_expression(raw.trim(), position)?;
+                nodes.push(Node::Var { lookup, filters, escape: false });
+            } else {
+                let (lookup, filters) = parse_expression(tag, position)?;
+                nodes.push(Node::Var { lookup, filters, escape: true });
+            }
+
+            rest = &rest[consumed..];
+            offset += consumed;
+
+        }
+
+        if let Some((name, position, ..)) = stack.pop() {
+            return Err(TemplateError { position, message: format!("section '{name}' is never closed") });
+        }
+
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+
+        Ok(Self { nodes })
+
+    }
+
+    pub fn render(&self, vars: &Value) -> String {
+        let mut out = String::new();
+        let mut stack = vec![Frame { value: vars, index: None }];
+        render_nodes(&self.nodes, &mut stack, &mut out);
+        out
+    }
+
+}
+
+
+pub fn render_template(template: &str, vars: &Value) -> Result<String, TemplateError> {
+    Ok(Template::compile(template)?.render(vars))
+}
+
+
+pub fn html_escape(stringin: &str) -> String {
+    let mut out = String::with_capacity(stringin.len());
+    for c in stringin.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+
+fn parse_lookup(name: &str, position: usize) -> Result<Lookup, TemplateError> {
+    match name {
+        "." => Ok(Lookup::Current),
+        "@index" => Ok(Lookup::Index),
+        "" => Err(TemplateError { position, message: "empty tag".to_string() }),
+        _ => parse_path(name)
+            .map(Lookup::Path)
+            .map_err(|e| TemplateError { position, message: e.to_string() }),
+    }
+}
+
+
+// splits on '|' outside of quotes...
+fn split_pipes(expression: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in expression.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '|') => {
+                out.push(expression[start..i].trim());
+                start = i + 1;
+            },
+            _ => {}
+        }
+    }
+    out.push(expression[start..].trim());
+    out
+}
+
+
+fn unquote(arg: &str) -> &str {
+    let arg = arg.trim();
+    for quote in ['"', '\''] {
+        if arg.len() >= 2 && arg.starts_with(quote) && arg.ends_with(quote) {
+            return &arg[1..arg.len() - 1];
+        }
+    }
+    arg
+}
+
+
+fn parse_expression(expression: &str, position: usize) -> Result<(Lookup, Vec<Filter>), TemplateError> {
+
+    let mut parts = split_pipes(expression).into_iter();
+    let lookup = parse_lookup(parts.next().unwrap_or_default(), position)?;
+
+    let filters = parts
+        .map(|part| {
+            let (name, arg) = match part.split_once(':') {
+                Some((name, arg)) => (name.trim(), Some(unquote(arg))),
+                None => (part, None),
+            };
+            match (name, arg) {
+                ("upper", None) => Ok(Filter::Upper),
+                ("lower", None) => Ok(Filter::Lower),
+                ("trim", None) => Ok(Filter::Trim),
+                ("capitalize", None) => Ok(Filter::Capitalize),
+                ("length", None) => Ok(Filter::Length),
+                ("json", None) => Ok(Filter::Json),
+                ("default", Some(arg)) => Ok(Filter::Default(arg.to_string())),
+                ("date", arg) => {
+                    // chrono panics while rendering an invalid format, so reject it up front...
+                    let format = arg.unwrap_or("%Y-%m-%d");
+                    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                        return Err(TemplateError { position, message: format!("invalid date format '{format}'") });
+                    }
+                    Ok(Filter::Date(format.to_string()))
+                },
+                ("truncate", Some(arg)) => arg.parse::<usize>().map(Filter::Truncate).map_err(|_| TemplateError {
+                    position,
+                    message: format!("truncate expects a number, found '{arg}'"),
+                }),
+                _ => Err(TemplateError { position, message: format!("unknown filter '{part}'") }),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((lookup, filters))
+
+}
+
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+
+// stringifies the same way recurse_value does, nulls become empty...
+fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+
+fn resolve<'a>(lookup: &Lookup, stack: &[Frame<'a>]) -> Option<Value> {
+    let frame = stack.last()?;
+    match lookup {
+        Lookup::Current => Some(frame.value.clone()),
+        Lookup::Index => stack.iter().rev().find_map(|f| f.index).map(Value::from),
+        Lookup::Path(segments) => resolve_ref(segments, stack).cloned(),
+    }
+}
+
+
+// walks the context stack from the innermost frame outwards...
+fn resolve_ref<'a>(segments: &[PathSegment], stack: &[Frame<'a>]) -> Option<&'a Value> {
+    stack.iter().rev().find_map(|frame| {
+        let first = segments.first()?;
+        let head = match first {
+            PathSegment::Key(key) => frame.value.as_object()?.get(key)?,
+            PathSegment::Index(index) => frame.value.as_array()?.get(*index)?,
+        };
+        get_segments(head, &segments[1..])
+    })
+}
+
+
+fn format_date(value: &Value, format: &str) -> Option<String> {
+    let epoch = match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => match s.trim().parse::<i64>() {
+            Ok(n) => Some(n),
+            Err(_) => return DateTime::parse_from_rfc3339(s.trim()).ok().map(|d| d.format(format).to_string()),
+        },
+        _ => None,
+    }?;
+    DateTime::from_timestamp(epoch, 0).map(|d| d.format(format).to_string())
+}
+
+
+fn apply_filter(filter: &Filter, value: Value) -> Value {
+    match filter {
+        Filter::Upper => Value::from(value_to_text(&value).to_uppercase()),
+        Filter::Lower => Value::from(value_to_text(&value).to_lowercase()),
+        Filter::Trim => Value::from(value_to_text(&value).trim()),
+        Filter::Capitalize => {
+            let text = value_to_text(&value);
+            let mut chars = text.chars();
+            match chars.next() {
+                Some(first) => Value::from(first.to_uppercase().chain(chars).collect::<String>()),
+                None => Value::from(text),
+            }
+        },
+        Filter::Length => Value::from(match &value {
+            Value::Array(a) => a.len(),
+            Value::Object(o) => o.len(),
+            Value::Null => 0,
+            _ => value_to_text(&value).chars().count(),
+        }),
+        Filter::Json => Value::from(value.to_string()),
+        Filter::Truncate(max) => {
+            let text = value_to_text(&value);
+            if text.chars().count() > *max {
+                Value::from(format!("{}...", text.chars().take(*max).collect::<String>()))
+            } else {
+                Value::from(text)
+            }
+        },
+        Filter::Default(fallback) => {
+            if is_truthy(&value) { value } else { Value::from(fallback.as_str()) }
+        },
+        Filter::Date(format) => format_date(&value, format).map(Value::from).unwrap_or(value),
+    }
+}
+
+
+fn render_nodes<'a>(nodes: &'a [Node], stack: &mut Vec<Frame<'a>>, out: &mut String) {
+
+    for node in nodes {
+
+        match node {
+
+            Node::Text(text) => out.push_str(text),
+
+            Node::Var { lookup, filters, escape } => {
+                let value = resolve(lookup, stack).unwrap_or(Value::Null);
+                let value = filters.iter().fold(value, |v, f| apply_filter(f, v));
+                let text = value_to_text(&value);
+                if *escape {
+                    out.push_str(&html_escape(&text));
+                } else {
+                    out.push_str(&text);
+                }
+            },
+
+            Node::Section { lookup, inverted, children } => {
+
+                let target: Option<&'a Value> = match lookup {
+                    Lookup::Path(segments) => resolve_ref(segments, stack),
+                    Lookup::Current => stack.last().map(|f| f.value),
+                    Lookup::Index => None,
+                };
+
+                let truthy = target.is_some_and(is_truthy);
+
+                if *inverted {
+                    if !truthy {
+                        render_nodes(children, stack, out);
+                    }
+                    continue;
+                }
+
+                if !truthy {
+                    continue;
+                }
+
+                match target {
+                    Some(Value::Array(items)) => {
+                        for (index, item) in items.iter().enumerate() {
+                            stack.push(Frame { value: item, index: Some(index) });
+                            render_nodes(children, stack, out);
+                            stack.pop();
+                        }
+                    },
+                    Some(value @ Value::Object(_)) => {
+                        stack.push(Frame { value, index: None });
+                        render_nodes(children, stack, out);
+                        stack.pop();
+                    },
+                    _ => render_nodes(children, stack, out),
+                }
+
+            },
+
+        }
+
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod test {
+
+    use serde_json::json;
+
+    use super::{Template, render_template};
+
+    #[test]
+    fn templatetests() {
+
+        let vars = json!({
+            "name": "sam <admin>",
+            "html": "<b>hi</b>",
+            "created": 0,
+            "items": [{"sku": "a1", "qty": 2}, {"sku": "b2", "qty": 1}],
+            "empty": [],
+            "company": {"title": "acme"}
+        });
+
+        assert_eq!(render_template("Hi {{name}}", &vars).unwrap(), "Hi sam &lt;admin&gt;");
+        assert_eq!(render_template("{{{html}}}{{& html}}", &vars).unwrap(), "<b>hi</b><b>hi</b>");
+        assert_eq!(
+            render_template("{{#items}}{{@index}}:{{sku}}x{{qty}} {{/items}}", &vars).unwrap(),
+            "0:a1x2 1:b2x1 "
+        );
+        assert_eq!(render_template("{{^empty}}none{{/empty}}{{#empty}}some{{/empty}}", &vars).unwrap(), "none");
+        assert_eq!(render_template("{{#company}}{{title | upper}} {{name | truncate:3}}{{/company}}", &vars).unwrap(), "ACME sam...");
+        assert_eq!(render_template("{{missing | default:\"n/a\"}}", &vars).unwrap(), "n/a");
+        assert_eq!(render_template("{{created | date:\"%Y-%m-%d\"}}{{! ignored }}", &vars).unwrap(), "1970-01-01");
+        assert_eq!(render_template("{{items[1].sku}}", &vars).unwrap(), "b2");
+
+        assert!(Template::compile("{{#items}}open").is_err());
+        assert!(Template::compile("{{#a}}{{/b}}").is_err());
+        assert!(Template::compile("{{name | shout}}").is_err());
+        assert!(Template::compile("{{created | date:\"%Q\"}}").is_err());
+        assert!(render_template("{{created | date:\"%Y %\"}}", &vars).is_err());
+
+    }
+
+}
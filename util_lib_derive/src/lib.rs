@@ -0,0 +1,266 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Expr, ExprLit, Fields, Lit, LitStr, parse_macro_input};
+
+
+// #[derive(EnumType)] for fieldless enums, generating the EnumType impl, conversions to and from
+// the repr, TryFrom<&str> and string based serde impls from one list of variants...
+//
+//   #[derive(Clone, Copy, PartialEq, EnumType)]
+//   #[repr(u16)]
+//   pub enum Status {
+//       #[enum_type(default)]
+//       Ok = 200,
+//       #[enum_type(rename = "not-found")]
+//       NotFound = 404,
+//   }
+//
+// names default to the lowercased variant name. discriminants may have gaps and the repr may be
+// u8 (the default), u16, u32 or u64. the default variant (the first one unless marked) is used
+// for unknown discriminants and by EnumType::from_str for unknown names. on the enum,
+// #[enum_type(skip_serde)] leaves serde to an existing derive and #[enum_type(skip_try_from)]
+// leaves out TryFrom<&str> for enums that already implement From<&str>.
+
+
+struct Variant {
+    ident: syn::Ident,
+    name: String,
+    discriminant: u64,
+    default: bool,
+}
+
+
+struct Options {
+    repr: syn::Ident,
+    max: u64,
+    skip_serde: bool,
+    skip_try_from: bool,
+}
+
+
+#[proc_macro_derive(EnumType, attributes(enum_type))]
+pub fn derive_enum_type(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+
+fn literal_discriminant(expr: &Expr) -> syn::Result<u64> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(value), .. }) => value.base10_parse::<u64>(),
+        _ => Err(Error::new_spanned(expr, "EnumType discriminants must be integer literals")),
+    }
+}
+
+
+fn parse_options(input: &DeriveInput) -> syn::Result<Options> {
+
+    let mut options = Options { repr: syn::Ident::new("u8", Span::call_site()), max: u8::MAX.into(), skip_serde: false, skip_try_from: false };
+
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                let Some(ident) = meta.path.get_ident() else {
+                    return Ok(());
+                };
+                options.max = match ident.to_string().as_str() {
+                    "u8" => u8::MAX.into(),
+                    "u16" => u16::MAX.into(),
+                    "u32" => u32::MAX.into(),
+                    "u64" => u64::MAX,
+                    "C" | "Rust" => return Ok(()),
+                    _ => return Err(meta.error("EnumType supports u8, u16, u32 and u64 representations")),
+                };
+                options.repr = ident.clone();
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("enum_type") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip_serde") {
+                    options.skip_serde = true;
+                    Ok(())
+                } else if meta.path.is_ident("skip_try_from") {
+                    options.skip_try_from = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip_serde` or `skip_try_from`"))
+                }
+            })?;
+        }
+    }
+
+    Ok(options)
+
+}
+
+
+fn parse_variants(data: &syn::DataEnum, options: &Options) -> syn::Result<Vec<Variant>> {
+
+    let mut out: Vec<Variant> = Vec::with_capacity(data.variants.len());
+    let mut next: Option<u64> = Some(0);
+
+    for variant in &data.variants {
+
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(variant, "EnumType variants cannot hold fields"));
+        }
+
+        let discriminant = match &variant.discriminant {
+            Some((_, expr)) => literal_discriminant(expr)?,
+            None => next.ok_or_else(|| Error::new_spanned(variant, "EnumType discriminant overflows"))?,
+        };
+        if discriminant > options.max {
+            return Err(Error::new_spanned(variant, format!("EnumType discriminant does not fit in a {}", options.repr)));
+        }
+        next = discriminant.checked_add(1);
+
+        let mut name = variant.ident.to_string().to_lowercase();
+        let mut default = false;
+
+        for attr in variant.attrs.iter().filter(|a| a.path().is_ident("enum_type")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    default = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `default` or `rename = \"...\"`"))
+                }
+            })?;
+        }
+
+        if out.iter().any(|v| v.name == name) {
+            return Err(Error::new_spanned(variant, format!("duplicate EnumType name \"{name}\"")));
+        }
+
+        out.push(Variant { ident: variant.ident.clone(), name, discriminant, default });
+
+    }
+
+    if out.iter().filter(|v| v.default).count() > 1 {
+        return Err(Error::new(Span::call_site(), "only one variant can be #[enum_type(default)]"));
+    }
+
+    Ok(out)
+
+}
+
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(input, "EnumType can only be derived for enums"));
+    };
+
+    let options = parse_options(input)?;
+    let variants = parse_variants(data, &options)?;
+    let Some(default) = variants.iter().find(|v| v.default).or(variants.first()) else {
+        return Err(Error::new_spanned(input, "EnumType needs at least one variant"));
+    };
+
+    let ident = &input.ident;
+    let repr = &options.repr;
+    let expecting = format!("a {ident} name");
+    let default = &default.ident;
+    let idents: Vec<_> = variants.iter().map(|v| &v.ident).collect();
+    let names: Vec<_> = variants.iter().map(|v| &v.name).collect();
+    let discriminants: Vec<_> = variants.iter().map(|v| proc_macro2::Literal::u64_unsuffixed(v.discriminant)).collect();
+
+    let serde = if options.skip_serde {
+        quote! {}
+    } else {
+        quote! {
+
+            impl ::util_lib::gentraits::__serde::Serialize for #ident {
+                fn serialize<S: ::util_lib::gentraits::__serde::Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+                    serializer.serialize_str(::util_lib::gentraits::EnumType::to_str(self))
+                }
+            }
+
+            impl<'de> ::util_lib::gentraits::__serde::Deserialize<'de> for #ident {
+                fn deserialize<D: ::util_lib::gentraits::__serde::Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+
+                    struct Visitor;
+
+                    impl<'de> ::util_lib::gentraits::__serde::de::Visitor<'de> for Visitor {
+
+                        type Value = #ident;
+
+                        fn expecting(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                            f.write_str(#expecting)
+                        }
+
+                        fn visit_str<E: ::util_lib::gentraits::__serde::de::Error>(self, value: &str) -> ::std::result::Result<#ident, E> {
+                            <#ident as ::util_lib::gentraits::EnumType>::try_from_name(value)
+                                .map_err(|_| E::unknown_variant(value, &[#(#names),*]))
+                        }
+
+                    }
+
+                    deserializer.deserialize_str(Visitor)
+
+                }
+            }
+
+        }
+    };
+
+    let try_from = if options.skip_try_from {
+        quote! {}
+    } else {
+        quote! {
+            impl ::std::convert::TryFrom<&str> for #ident {
+
+                type Error = ::util_lib::gentraits::EnumTypeError;
+
+                fn try_from(value: &str) -> ::std::result::Result<Self, Self::Error> {
+                    <Self as ::util_lib::gentraits::EnumType>::try_from_name(value)
+                }
+
+            }
+        }
+    };
+
+    Ok(quote! {
+
+        impl ::std::convert::From<#ident> for #repr {
+            fn from(value: #ident) -> Self {
+                value as #repr
+            }
+        }
+
+        impl ::std::convert::From<#repr> for #ident {
+            fn from(value: #repr) -> Self {
+                match value {
+                    #(#discriminants => Self::#idents,)*
+                    _ => Self::#default,
+                }
+            }
+        }
+
+        #try_from
+
+        impl ::util_lib::gentraits::EnumType for #ident {
+
+            type Repr = #repr;
+
+            const VARIANTS: &'static [(Self, &'static str)] = &[#((Self::#idents, #names)),*];
+
+            fn default() -> Self {
+                Self::#default
+            }
+
+            fn to_repr(self) -> #repr {
+                self as #repr
+            }
+
+        }
+
+        #serde
+
+    })
+
+}